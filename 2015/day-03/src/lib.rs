@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct Santa {
@@ -7,6 +7,18 @@ pub struct Santa {
 }
 
 impl Santa {
+    pub fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+
+    pub fn x(&self) -> isize {
+        self.x
+    }
+
+    pub fn y(&self) -> isize {
+        self.y
+    }
+
     pub fn fly(&mut self, direction: char) {
         match direction {
             '^' => self.y += 1,
@@ -18,32 +30,110 @@ impl Santa {
     }
 }
 
-fn houses(steps: impl Iterator<Item = char>) -> HashSet<Santa> {
-    steps
-        .fold(
-            (HashSet::from([Santa::default()]), Santa::default()),
-            |(mut houses, mut santa), step| {
-                santa.fly(step);
-                houses.insert(santa);
-                (houses, santa)
-            },
-        )
-        .0
+/// Characters used to draw a heatmap, from the fewest visits to the most.
+const HEAT: &[u8] = b" .:-=+*#%@";
+
+/// Couriers taking turns to follow the same list of directions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delivery {
+    couriers: Vec<Santa>,
+    turn: usize,
+    visits: HashMap<Santa, usize>,
+    houses: Vec<HashSet<Santa>>,
+}
+
+impl Delivery {
+    /// Starts `couriers` couriers at the same house, which each of them delivers to.
+    pub fn new(couriers: usize) -> Self {
+        assert!(couriers > 0, "somebody has to deliver the presents");
+        Self {
+            couriers: vec![Santa::default(); couriers],
+            turn: 0,
+            visits: HashMap::from([(Santa::default(), couriers)]),
+            houses: vec![HashSet::from([Santa::default()]); couriers],
+        }
+    }
+
+    /// Hands out directions to each courier in turn. Anything that isn't a direction is
+    /// skipped without using up a turn.
+    pub fn fly(&mut self, directions: impl IntoIterator<Item = char>) {
+        for direction in directions {
+            if !matches!(direction, '^' | 'v' | '>' | '<') {
+                continue;
+            }
+            let courier = &mut self.couriers[self.turn];
+            courier.fly(direction);
+            *self.visits.entry(*courier).or_default() += 1;
+            self.houses[self.turn].insert(*courier);
+            self.turn = (self.turn + 1) % self.couriers.len();
+        }
+    }
+
+    /// Returns how many presents each house received.
+    pub fn visits(&self) -> &HashMap<Santa, usize> {
+        &self.visits
+    }
+
+    /// Returns how many houses received at least one present.
+    pub fn houses(&self) -> usize {
+        self.visits.len()
+    }
+
+    /// Returns how many different houses each courier delivered to.
+    pub fn courier_houses(&self) -> Vec<usize> {
+        self.houses.iter().map(HashSet::len).collect()
+    }
+
+    /// Returns the bottom left and top right corners of every house visited.
+    pub fn bounds(&self) -> (Santa, Santa) {
+        self.visits
+            .keys()
+            .fold((Santa::default(), Santa::default()), |(min, max), house| {
+                (
+                    Santa::new(min.x.min(house.x), min.y.min(house.y)),
+                    Santa::new(max.x.max(house.x), max.y.max(house.y)),
+                )
+            })
+    }
+
+    /// Draws the visited houses with north at the top, using denser characters for houses
+    /// which received more presents.
+    pub fn heatmap(&self) -> String {
+        let (min, max) = self.bounds();
+        let most = self.visits.values().copied().max().unwrap_or_default();
+        let mut heatmap = String::new();
+        for y in (min.y..=max.y).rev() {
+            for x in min.x..=max.x {
+                let heat = match self.visits.get(&Santa::new(x, y)) {
+                    None => 0,
+                    Some(_) if most == 1 => HEAT.len() - 1,
+                    Some(&visits) => 1 + (visits - 1) * (HEAT.len() - 2) / (most - 1),
+                };
+                heatmap.push(HEAT[heat] as char);
+            }
+            heatmap.push('\n');
+        }
+        heatmap
+    }
+}
+
+fn deliver(input: &str, couriers: usize) -> usize {
+    let mut delivery = Delivery::new(couriers);
+    delivery.fly(input.chars());
+    delivery.houses()
 }
 
 pub fn part_one(input: &'static str) -> usize {
-    houses(input.chars()).len()
+    deliver(input, 1)
 }
 
 pub fn part_two(input: &'static str) -> usize {
-    houses(input.chars().skip(1).step_by(2))
-        .union(&houses(input.chars().step_by(2)))
-        .count()
+    deliver(input, 2)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{part_one, part_two};
+    use super::*;
 
     #[test]
     fn test_part_one() {
@@ -58,4 +148,20 @@ mod tests {
         assert_eq!(part_two("^>v<"), 3);
         assert_eq!(part_two("^v^v^v^v^v"), 11);
     }
+
+    #[test]
+    fn test_delivery() {
+        let mut delivery = Delivery::new(3);
+        delivery.fly(">>>^^\n<<<<".chars());
+        assert_eq!(delivery.houses(), 5);
+        assert_eq!(delivery.visits()[&Santa::default()], 4);
+        assert_eq!(delivery.visits()[&Santa::new(1, 0)], 3);
+        assert_eq!(delivery.courier_houses(), [4, 4, 3]);
+        assert_eq!(delivery.bounds(), (Santa::new(-1, 0), Santa::new(1, 1)));
+        assert_eq!(delivery.heatmap(), " --\n.@*\n");
+
+        let mut delivery = Delivery::new(1);
+        delivery.fly("^>".chars());
+        assert_eq!(delivery.heatmap(), "@@\n@ \n");
+    }
 }
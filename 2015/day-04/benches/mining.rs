@@ -0,0 +1,24 @@
+use aoc_2015_day_04::{mine_adventcoin, mine_adventcoin_naive};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const PREFIX: &str = "abcdef";
+
+fn bench_mining(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mining");
+    // Six zeroes takes long enough that the default sample count would run for ages
+    group.sample_size(10);
+    for zeroes in [5, 6] {
+        group.bench_with_input(BenchmarkId::new("naive", zeroes), &zeroes, |b, &zeroes| {
+            b.iter(|| mine_adventcoin_naive(PREFIX, zeroes))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("parallel", zeroes),
+            &zeroes,
+            |b, &zeroes| b.iter(|| mine_adventcoin(PREFIX, zeroes)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_mining);
+criterion_main!(benches);
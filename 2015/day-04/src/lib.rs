@@ -1,4 +1,10 @@
-fn mine_adventcoin(prefix: &str, zeroes: usize) -> usize {
+use rayon::prelude::*;
+
+/// How many nonces each thread checks at a time.
+const CHUNK_SIZE: usize = 1 << 14;
+
+/// The original single-threaded miner, kept around to benchmark against.
+pub fn mine_adventcoin_naive(prefix: &str, zeroes: usize) -> usize {
     let zeroes: String = "0".repeat(zeroes);
     (0..)
         .map(|n| {
@@ -7,7 +13,60 @@ fn mine_adventcoin(prefix: &str, zeroes: usize) -> usize {
                 format!("{:x}", md5::compute(format!("{}{}", prefix.trim(), n))),
             )
         })
-        .find_map(|(n, hash)| hash.starts_with(&zeroes).then(|| n))
+        .find_map(|(n, hash)| hash.starts_with(&zeroes).then_some(n))
+        .unwrap()
+}
+
+/// Returns `true` if the digest's hex representation starts with `zeroes` zeroes.
+fn has_leading_zeroes(digest: &[u8; 16], zeroes: usize) -> bool {
+    let (bytes, nibble) = (zeroes / 2, zeroes % 2);
+    digest[..bytes].iter().all(|&b| b == 0) && (nibble == 0 || digest[bytes] < 0x10)
+}
+
+/// Writes `n` in decimal to the end of `buf`, returning the digits.
+fn decimal(mut n: usize, buf: &mut [u8; 20]) -> &[u8] {
+    let mut i = buf.len();
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            return &buf[i..];
+        }
+    }
+}
+
+/// Returns the first nonce in `nonces` whose hash is valid, starting from an already hashed
+/// prefix.
+fn search(prefix: &md5::Context, zeroes: usize, nonces: std::ops::Range<usize>) -> Option<usize> {
+    let mut buf = [0; 20];
+    nonces.into_iter().find(|&n| {
+        let mut context = prefix.clone();
+        context.consume(decimal(n, &mut buf));
+        has_leading_zeroes(&context.compute(), zeroes)
+    })
+}
+
+/// Finds the smallest nonce which, appended to `prefix`, gives an MD5 hash starting with
+/// `zeroes` hex zeroes. The nonce space is searched in chunks across every thread.
+pub fn mine_adventcoin(prefix: &str, zeroes: usize) -> usize {
+    assert!(zeroes <= 32, "an MD5 hash only has 32 hex digits");
+    let mut context = md5::Context::new();
+    context.consume(prefix.trim());
+
+    let threads = rayon::current_num_threads();
+    (0..)
+        .step_by(CHUNK_SIZE * threads)
+        .find_map(|start| {
+            // Every chunk in the batch is searched, so the smallest find is the answer
+            (0..threads)
+                .into_par_iter()
+                .filter_map(|i| {
+                    let chunk = start + i * CHUNK_SIZE;
+                    search(&context, zeroes, chunk..chunk + CHUNK_SIZE)
+                })
+                .min()
+        })
         .unwrap()
 }
 
@@ -21,11 +80,30 @@ pub fn part_two(input: &'static str) -> usize {
 
 #[cfg(test)]
 mod tests {
-    use super::part_one;
+    use super::*;
 
     #[test]
     fn test_part_one() {
         assert_eq!(part_one("abcdef"), 609043);
         assert_eq!(part_one("pqrstuv"), 1048970);
     }
+
+    #[test]
+    fn test_difficulty() {
+        for zeroes in 0..5 {
+            assert_eq!(
+                mine_adventcoin("abcdef", zeroes),
+                mine_adventcoin_naive("abcdef", zeroes)
+            );
+        }
+        assert_eq!(mine_adventcoin("abcdef\n", 0), 0);
+
+        let mut digest = [0xff; 16];
+        assert!(has_leading_zeroes(&digest, 0));
+        assert!(!has_leading_zeroes(&digest, 1));
+        digest[0] = 0x0f;
+        assert!(has_leading_zeroes(&digest, 1));
+        assert!(!has_leading_zeroes(&digest, 2));
+        assert!(has_leading_zeroes(&[0; 16], 32));
+    }
 }
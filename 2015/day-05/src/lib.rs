@@ -1,17 +1,224 @@
-use itertools::Itertools;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use thiserror::Error;
+
+/// The rules from part one, in the format understood by [`RuleSet::parse`].
+pub const PART_ONE_RULES: &str = "
+vowels: count aeiou 3
+double: run 2
+naughty-pairs: not contains ab cd pq xy
+";
+
+/// The rules from part two, in the format understood by [`RuleSet::parse`].
+pub const PART_TWO_RULES: &str = "
+pair: repeated-pair
+sandwich: gap 1
+";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("line {line}: {kind}")]
+pub struct RuleError {
+    pub line: usize,
+    pub kind: RuleErrorKind,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RuleErrorKind {
+    #[error("expected `name: rule`")]
+    MissingName,
+    #[error("unknown rule `{0}`")]
+    UnknownRule(String),
+    #[error("invalid arguments `{0}`")]
+    InvalidArguments(String),
+}
+
+/// A check on a string, each of which runs in linear time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// At least `count` characters from `chars`. Written `count <chars> <count>`.
+    Count { chars: Vec<char>, count: usize },
+    /// The same character at least `length` times in a row. Written `run <length>`.
+    Run(usize),
+    /// Any of the substrings. Written `contains <substring>...`.
+    Contains(Vec<String>),
+    /// A pair of characters appearing twice without overlapping. Written `repeated-pair`.
+    RepeatedPair,
+    /// A character repeated with exactly `gap` characters between. Written `gap <gap>`.
+    Gap(usize),
+    /// Written `not <rule>`.
+    Not(Box<Rule>),
+    /// Written `any <rule> | <rule>...`.
+    Any(Vec<Rule>),
+    /// Written `all <rule> & <rule>...`.
+    All(Vec<Rule>),
+}
+
+impl Rule {
+    pub fn matches(&self, s: &str) -> bool {
+        match self {
+            Rule::Count { chars, count } => {
+                s.chars().filter(|c| chars.contains(c)).count() >= *count
+            }
+            Rule::Run(length) => {
+                let mut run = 0;
+                let mut previous = None;
+                for c in s.chars() {
+                    run = if previous == Some(c) { run + 1 } else { 1 };
+                    if run >= *length {
+                        return true;
+                    }
+                    previous = Some(c);
+                }
+                *length == 0
+            }
+            Rule::Contains(substrings) => substrings.iter().any(|sub| s.contains(sub.as_str())),
+            Rule::RepeatedPair => {
+                // Remember where each pair first ends, so later copies can't overlap it
+                let chars: Vec<char> = s.chars().collect();
+                let mut seen = HashMap::new();
+                chars.windows(2).enumerate().any(|(i, pair)| {
+                    let first = *seen.entry((pair[0], pair[1])).or_insert(i);
+                    i > first + 1
+                })
+            }
+            Rule::Gap(gap) => {
+                let chars: Vec<char> = s.chars().collect();
+                chars.windows(gap + 2).any(|w| w[0] == w[gap + 1])
+            }
+            Rule::Not(rule) => !rule.matches(s),
+            Rule::Any(rules) => rules.iter().any(|rule| rule.matches(s)),
+            Rule::All(rules) => rules.iter().all(|rule| rule.matches(s)),
+        }
+    }
+}
+
+impl std::str::FromStr for Rule {
+    type Err = RuleErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, args) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let invalid = || RuleErrorKind::InvalidArguments(args.trim().to_string());
+        let words: Vec<&str> = args.split_whitespace().collect();
+        let number = |word: &str| word.parse::<usize>().map_err(|_| invalid());
+
+        match (name, words.as_slice()) {
+            ("not", _) => Ok(Rule::Not(Box::new(args.parse()?))),
+            ("any", _) => Ok(Rule::Any(
+                args.split('|').map(str::parse).collect::<Result<_, _>>()?,
+            )),
+            ("all", _) => Ok(Rule::All(
+                args.split('&').map(str::parse).collect::<Result<_, _>>()?,
+            )),
+            ("count", [chars, count]) => Ok(Rule::Count {
+                chars: chars.chars().collect(),
+                count: number(count)?,
+            }),
+            ("run", [length]) => Ok(Rule::Run(number(length)?)),
+            ("contains", [_, ..]) => Ok(Rule::Contains(
+                words.iter().map(|word| word.to_string()).collect(),
+            )),
+            ("repeated-pair", []) => Ok(Rule::RepeatedPair),
+            ("gap", [gap]) => Ok(Rule::Gap(number(gap)?)),
+            ("count" | "run" | "contains" | "repeated-pair" | "gap", _) => Err(invalid()),
+            _ => Err(RuleErrorKind::UnknownRule(name.to_string())),
+        }
+    }
+}
+
+/// Named rules which a nice string has to pass all of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSet {
+    rules: Vec<(String, Rule)>,
+}
+
+impl RuleSet {
+    /// Parses one `name: rule` per line. Blank lines and anything after a `#` are ignored.
+    pub fn parse(config: &str) -> Result<Self, RuleError> {
+        let mut rules = Vec::new();
+        for (i, line) in config.lines().enumerate() {
+            let error = |kind| RuleError { line: i + 1, kind };
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, rule) = line
+                .split_once(':')
+                .ok_or_else(|| error(RuleErrorKind::MissingName))?;
+            rules.push((name.trim().to_string(), rule.parse().map_err(error)?));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn rules(&self) -> &[(String, Rule)] {
+        &self.rules
+    }
+
+    pub fn is_nice(&self, s: &str) -> bool {
+        self.rules.iter().all(|(_, rule)| rule.matches(s))
+    }
+
+    /// Checks every rule, rather than stopping at the first failure.
+    pub fn check<'a>(&'a self, s: &str) -> Report<'a> {
+        Report {
+            results: self
+                .rules
+                .iter()
+                .map(|(name, rule)| (name.as_str(), rule.matches(s)))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report<'a> {
+    results: Vec<(&'a str, bool)>,
+}
+
+impl<'a> Report<'a> {
+    pub fn results(&self) -> &[(&'a str, bool)] {
+        &self.results
+    }
+
+    pub fn is_nice(&self) -> bool {
+        self.results.iter().all(|&(_, passed)| passed)
+    }
+
+    pub fn passed(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.results
+            .iter()
+            .filter_map(|&(name, passed)| passed.then_some(name))
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.results
+            .iter()
+            .filter_map(|&(name, passed)| (!passed).then_some(name))
+    }
+}
+
+impl std::fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, passed) in &self.results {
+            writeln!(f, "{}: {}", name, if *passed { "pass" } else { "fail" })?;
+        }
+        Ok(())
+    }
+}
 
 const VOWELS: [char; 5] = ['a', 'e', 'i', 'o', 'u'];
-const BLACKLIST: [&str; 4] = ["ab", "cd", "pq", "xy"];
 
 pub fn count_vowels(s: &str) -> usize {
     s.chars().filter(|c| VOWELS.contains(c)).count()
 }
 
 pub fn longest_run(s: &str) -> usize {
-    s.chars()
-        .tuple_windows()
-        .fold((1, 1), |(mut current_run, longest_run), (a, b)| {
-            if a == b {
+    let chars: Vec<char> = s.chars().collect();
+    chars
+        .windows(2)
+        .fold((1, 1), |(mut current_run, longest_run), pair| {
+            if pair[0] == pair[1] {
                 current_run += 1;
             } else {
                 current_run = 1;
@@ -25,40 +232,37 @@ pub fn excludes(s: &str, blacklist: &[&str]) -> bool {
     blacklist.iter().all(|e| !s.contains(e))
 }
 
-fn has_double_letter(s: &str) -> bool {
-    s.chars()
-        .tuple_windows()
-        .map(|(a, b)| format!("{}{}", a, b))
-        .any(|pair| {
-            s.match_indices(&pair)
-                .combinations(2)
-                .any(|matches| matches[0].0 > matches[1].0 + 1 || matches[0].0 + 1 < matches[1].0)
-        })
+fn part_one_rules() -> &'static RuleSet {
+    static RULES: OnceLock<RuleSet> = OnceLock::new();
+    RULES.get_or_init(|| RuleSet::parse(PART_ONE_RULES).unwrap())
 }
 
-fn has_double_pairs(s: &str) -> bool {
-    s.chars().tuple_windows().any(|(a, _, c)| a == c)
+fn part_two_rules() -> &'static RuleSet {
+    static RULES: OnceLock<RuleSet> = OnceLock::new();
+    RULES.get_or_init(|| RuleSet::parse(PART_TWO_RULES).unwrap())
 }
 
 pub fn is_nice(s: &str) -> bool {
-    count_vowels(s) >= 3 && longest_run(s) >= 2 && excludes(s, &BLACKLIST)
+    part_one_rules().is_nice(s)
 }
 
 pub fn is_nice_revised(s: &str) -> bool {
-    has_double_pairs(s) && has_double_letter(s)
+    part_two_rules().is_nice(s)
 }
-pub fn part_one(input: &'static str) -> usize {
+
+fn count_nice(input: &'static str, rules: &RuleSet) -> usize {
     parse_input(input)
         .into_iter()
-        .filter(|line| is_nice(line))
+        .filter(|line| rules.is_nice(line))
         .count()
 }
 
+pub fn part_one(input: &'static str) -> usize {
+    count_nice(input, part_one_rules())
+}
+
 pub fn part_two(input: &'static str) -> usize {
-    parse_input(input)
-        .into_iter()
-        .filter(|line| is_nice_revised(line))
-        .count()
+    count_nice(input, part_two_rules())
 }
 
 fn parse_input(input: &'static str) -> Vec<&'static str> {
@@ -67,7 +271,7 @@ fn parse_input(input: &'static str) -> Vec<&'static str> {
 
 #[cfg(test)]
 mod tests {
-    use super::{is_nice, is_nice_revised};
+    use super::*;
 
     #[test]
     fn test_is_nice() {
@@ -85,4 +289,67 @@ mod tests {
         assert_eq!(is_nice_revised("uurcxstgmygtbstg"), false);
         assert_eq!(is_nice_revised("ieodomkazucvgmuy"), false);
     }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(count_vowels("aeiouxyz"), 5);
+        assert_eq!(longest_run("abbbcc"), 3);
+        assert_eq!(longest_run(""), 1);
+        assert!(!excludes("aabb", &["ab", "cd"]));
+        assert!(excludes("acbd", &["ab", "cd"]));
+    }
+
+    #[test]
+    fn test_rules() {
+        for (rule, matching, other) in [
+            ("run 3", "abbbc", "abbcc"),
+            ("repeated-pair", "aabcaa", "aaa"),
+            ("gap 0", "abba", "aba"),
+            ("gap 2", "abca", "abcda"),
+            ("not contains ab cd", "acbd", "xcdx"),
+            ("any run 2 | gap 1", "aba", "abc"),
+            ("all count xyz 2 & not run 2", "xzy", "xxy"),
+        ] {
+            let rule: Rule = rule.parse().unwrap();
+            assert!(rule.matches(matching), "{:?} {}", rule, matching);
+            assert!(!rule.matches(other), "{:?} {}", rule, other);
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let rules = RuleSet::parse(PART_ONE_RULES).unwrap();
+        let report = rules.check("haegwjzuvuyypxyu");
+        assert!(!report.is_nice());
+        assert_eq!(report.failed().collect::<Vec<_>>(), ["naughty-pairs"]);
+        assert_eq!(report.passed().collect::<Vec<_>>(), ["vowels", "double"]);
+        assert_eq!(
+            report.to_string(),
+            "vowels: pass\ndouble: pass\nnaughty-pairs: fail\n"
+        );
+    }
+
+    #[test]
+    fn test_config_errors() {
+        for (config, line, kind) in [
+            ("run 2", 1, RuleErrorKind::MissingName),
+            (
+                "# comment\n\nx: sparkle",
+                3,
+                RuleErrorKind::UnknownRule("sparkle".to_string()),
+            ),
+            (
+                "x: not run two",
+                1,
+                RuleErrorKind::InvalidArguments("two".to_string()),
+            ),
+            (
+                "x: repeated-pair 2",
+                1,
+                RuleErrorKind::InvalidArguments("2".to_string()),
+            ),
+        ] {
+            assert_eq!(RuleSet::parse(config), Err(RuleError { line, kind }));
+        }
+    }
 }
@@ -1,9 +1,22 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use image::RgbImage;
 use regex::Regex;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("line {0} doesn't start with `turn on`, `turn off` or `toggle`")]
+    UnknownInstruction(usize),
+    #[error("line {0} isn't of the form `x,y through x,y`")]
+    Malformed(usize),
+}
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-struct Point {
-    x: usize,
-    y: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
 }
 
 impl Point {
@@ -12,161 +25,328 @@ impl Point {
     }
 }
 
-#[derive(Debug)]
-struct Rectangle {
-    start: Point,
-    end: Point,
+/// A rectangle of lights, including both corners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rectangle {
+    pub start: Point,
+    pub end: Point,
 }
 
 impl Rectangle {
-    pub fn contains(&self, point: &Point) -> bool {
-        (point.x >= self.start.x && point.x <= self.end.x)
-            && (point.y >= self.start.y && point.y <= self.end.y)
+    pub fn new(start: Point, end: Point) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the columns and rows covered by the rectangle, clipped to a grid of the
+    /// given size. A rectangle whose end comes before its start doesn't cover anything.
+    pub fn clip(&self, width: usize, height: usize) -> (Range<usize>, Range<usize>) {
+        let span = |start: usize, end: usize, len: usize| {
+            let start = start.min(len);
+            start..end.saturating_add(1).clamp(start, len)
+        };
+        (
+            span(self.start.x, self.end.x, width),
+            span(self.start.y, self.end.y, height),
+        )
     }
 }
 
-#[derive(Debug)]
-enum InstructionKind {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionKind {
     On,
     Off,
     Toggle,
 }
 
-#[derive(Debug)]
-struct Instruction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
     pub kind: InstructionKind,
     pub rect: Rectangle,
 }
 
-enum Behaviour {
+/// What turning a light on, off or toggling it actually does.
+pub trait LightBehaviour {
+    type Light: Clone + Default;
+
+    fn turn_on(&self, light: &mut Self::Light);
+
+    fn turn_off(&self, light: &mut Self::Light);
+
+    fn toggle(&self, light: &mut Self::Light);
+
+    fn brightness(&self, light: &Self::Light) -> usize;
+
+    /// Returns the colour to render a light as, given the brightest light in the grid.
+    /// Defaults to shades of grey.
+    fn colour(&self, light: &Self::Light, max_brightness: usize) -> [u8; 3] {
+        let shade = (self.brightness(light) * 255)
+            .checked_div(max_brightness)
+            .unwrap_or_default() as u8;
+        [shade; 3]
+    }
+
+    fn execute(&self, kind: InstructionKind, light: &mut Self::Light) {
+        match kind {
+            InstructionKind::On => self.turn_on(light),
+            InstructionKind::Off => self.turn_off(light),
+            InstructionKind::Toggle => self.toggle(light),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behaviour {
     PartOne,
     PartTwo,
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Light(usize);
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Light(usize);
 
 impl Light {
-    pub fn turn_on(&mut self, behaviour: &Behaviour) {
-        match behaviour {
-            Behaviour::PartOne => self.0 = 1,
-            Behaviour::PartTwo => self.0 += 1,
+    #[must_use]
+    pub fn is_on(&self) -> bool {
+        self.0 > 0
+    }
+}
+
+impl LightBehaviour for Behaviour {
+    type Light = Light;
+
+    fn turn_on(&self, light: &mut Light) {
+        match self {
+            Behaviour::PartOne => light.0 = 1,
+            Behaviour::PartTwo => light.0 += 1,
         }
     }
 
-    pub fn turn_off(&mut self) {
-        self.0 = self.0.saturating_sub(1);
+    fn turn_off(&self, light: &mut Light) {
+        light.0 = light.0.saturating_sub(1);
     }
 
-    pub fn toggle(&mut self, behaviour: &Behaviour) {
-        match behaviour {
-            Behaviour::PartOne => self.0 = if self.is_on() { 0 } else { 1 },
-            Behaviour::PartTwo => self.0 += 2,
+    fn toggle(&self, light: &mut Light) {
+        match self {
+            Behaviour::PartOne => light.0 = if light.is_on() { 0 } else { 1 },
+            Behaviour::PartTwo => light.0 += 2,
         }
     }
 
-    pub fn execute(&mut self, instruction_kind: &InstructionKind, behaviour: &Behaviour) {
-        match instruction_kind {
-            InstructionKind::On => self.turn_on(behaviour),
-            InstructionKind::Off => self.turn_off(),
-            InstructionKind::Toggle => self.toggle(behaviour),
-        };
+    fn brightness(&self, light: &Light) -> usize {
+        light.0
     }
+}
 
-    #[must_use]
-    fn is_on(&self) -> bool {
-        self.0 > 0
+/// Part two's brightness rules, except lights can't go past `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dimmer {
+    pub max: usize,
+}
+
+impl LightBehaviour for Dimmer {
+    type Light = Light;
+
+    fn turn_on(&self, light: &mut Light) {
+        light.0 = (light.0 + 1).min(self.max);
+    }
+
+    fn turn_off(&self, light: &mut Light) {
+        light.0 = light.0.saturating_sub(1);
+    }
+
+    fn toggle(&self, light: &mut Light) {
+        light.0 = (light.0 + 2).min(self.max);
+    }
+
+    fn brightness(&self, light: &Light) -> usize {
+        light.0
     }
 }
 
-struct LightMatrix {
+/// The size of the grid in Santa's instructions.
+const GRID_SIZE: usize = 1000;
+
+pub struct LightMatrix<B: LightBehaviour = Behaviour> {
     width: usize,
-    lights: Vec<Light>,
+    height: usize,
+    behaviour: B,
+    lights: Vec<B::Light>,
 }
 
-impl LightMatrix {
-    pub fn new(width: usize, height: usize) -> Self {
+impl<B: LightBehaviour> LightMatrix<B> {
+    pub fn new(width: usize, height: usize, behaviour: B) -> Self {
         Self {
             width,
-            lights: Vec::from_iter(std::iter::repeat(Light(0)).take(width * height)),
+            height,
+            behaviour,
+            lights: vec![B::Light::default(); width * height],
         }
     }
 
-    pub fn execute(&mut self, instruction: &Instruction, behaviour: &Behaviour) {
-        self.lights
-            .iter_mut()
-            .enumerate()
-            .filter_map(|(i, light)| {
-                let x = i % self.width;
-                let y = i / self.width;
-                let point = Point::new(x, y);
-                instruction.rect.contains(&point).then(|| light)
-            })
-            .for_each(|light| light.execute(&instruction.kind, behaviour))
+    /// Creates a grid the size of the one in Santa's instructions.
+    pub fn with_behaviour(behaviour: B) -> Self {
+        Self::new(GRID_SIZE, GRID_SIZE, behaviour)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, point: Point) -> Option<&B::Light> {
+        (point.x < self.width)
+            .then(|| self.lights.get(point.y * self.width + point.x))
+            .flatten()
+    }
+
+    /// Applies an instruction to every light it covers. Anything outside of the grid is
+    /// ignored.
+    pub fn execute(&mut self, instruction: &Instruction) {
+        let (columns, rows) = instruction.rect.clip(self.width, self.height);
+        for y in rows {
+            let row = &mut self.lights[y * self.width..(y + 1) * self.width];
+            row[columns.clone()]
+                .iter_mut()
+                .for_each(|light| self.behaviour.execute(instruction.kind, light));
+        }
     }
 
     pub fn count_lit(&self) -> usize {
-        self.lights.iter().map(|Light(brightness)| brightness).sum()
+        self.lights
+            .iter()
+            .map(|light| self.behaviour.brightness(light))
+            .sum()
+    }
+
+    /// Sums the brightness of every light in `rect`.
+    pub fn region_brightness(&self, rect: &Rectangle) -> usize {
+        let (columns, rows) = rect.clip(self.width, self.height);
+        rows.map(|y| {
+            self.lights[y * self.width..(y + 1) * self.width][columns.clone()]
+                .iter()
+                .map(|light| self.behaviour.brightness(light))
+                .sum::<usize>()
+        })
+        .sum()
+    }
+
+    /// Counts how many lights there are at each brightness.
+    pub fn histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for light in &self.lights {
+            *histogram
+                .entry(self.behaviour.brightness(light))
+                .or_default() += 1;
+        }
+        histogram
+    }
+
+    pub fn to_image(&self) -> RgbImage {
+        let max_brightness = self
+            .lights
+            .iter()
+            .map(|light| self.behaviour.brightness(light))
+            .max()
+            .unwrap_or_default();
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let light = &self.lights[y as usize * self.width + x as usize];
+            image::Rgb(self.behaviour.colour(light, max_brightness))
+        })
     }
 }
 
+/// A parsed set of instructions, which can be run on any number of grids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+}
+
+impl Program {
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        Ok(Self {
+            instructions: parse_input(input)?,
+        })
+    }
+
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    pub fn run<B: LightBehaviour>(&self, matrix: &mut LightMatrix<B>) {
+        self.instructions
+            .iter()
+            .for_each(|instr| matrix.execute(instr));
+    }
+}
+
+/// Counts the brightness of a `width` by `height` grid once the instructions are followed.
+pub fn count_lit<B: LightBehaviour>(
+    input: &'static str,
+    width: usize,
+    height: usize,
+    behaviour: B,
+) -> usize {
+    let mut matrix = LightMatrix::new(width, height, behaviour);
+    Program::parse(input).unwrap().run(&mut matrix);
+    matrix.count_lit()
+}
+
 pub fn part_one(input: &'static str) -> usize {
-    let instructions = parse_input(input);
-    let mut matrix = LightMatrix::new(1000, 1000);
-    instructions
-        .iter()
-        .for_each(|instr| matrix.execute(instr, &Behaviour::PartOne));
+    let mut matrix = LightMatrix::with_behaviour(Behaviour::PartOne);
+    Program::parse(input).unwrap().run(&mut matrix);
     matrix.count_lit()
 }
 
 pub fn part_two(input: &'static str) -> usize {
-    let instructions = parse_input(input);
-    let mut matrix = LightMatrix::new(1000, 1000);
-    instructions
-        .iter()
-        .for_each(|instr| matrix.execute(instr, &Behaviour::PartTwo));
+    let mut matrix = LightMatrix::with_behaviour(Behaviour::PartTwo);
+    Program::parse(input).unwrap().run(&mut matrix);
     matrix.count_lit()
 }
 
-fn parse_input(input: &'static str) -> Vec<Instruction> {
-    input
-        .lines()
-        .filter_map(|s| match s.trim() {
-            "" => None,
-            _ => Some(s.trim()),
-        })
-        .map(|line| {
-            let kind = if line.starts_with("turn on") {
-                InstructionKind::On
-            } else if line.starts_with("turn off") {
-                InstructionKind::Off
-            } else if line.starts_with("toggle") {
-                InstructionKind::Toggle
-            } else {
-                unreachable!()
-            };
-
-            let captures: Vec<usize> = Regex::new(r#"(\d+),(\d+) through (\d+),(\d+)"#)
-                .unwrap()
-                .captures(line)
-                .unwrap()
-                .iter()
-                .skip(1)
-                .map(|n| n.unwrap().as_str().parse().unwrap())
-                .collect();
-            let rect = Rectangle {
-                start: Point::new(captures[0], captures[1]),
-                end: Point::new(captures[2], captures[3]),
-            };
-
-            Instruction { kind, rect }
-        })
-        .collect()
+fn parse_input(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    let corners = Regex::new(r#"^(\d+),(\d+) through (\d+),(\d+)$"#).unwrap();
+    let mut instructions = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (kind, rest) = if let Some(rest) = line.strip_prefix("turn on ") {
+            (InstructionKind::On, rest)
+        } else if let Some(rest) = line.strip_prefix("turn off ") {
+            (InstructionKind::Off, rest)
+        } else if let Some(rest) = line.strip_prefix("toggle ") {
+            (InstructionKind::Toggle, rest)
+        } else {
+            return Err(ParseError::UnknownInstruction(i + 1));
+        };
+
+        let captures: Vec<usize> = corners
+            .captures(rest.trim())
+            .and_then(|captures| {
+                captures
+                    .iter()
+                    .skip(1)
+                    .map(|n| n?.as_str().parse().ok())
+                    .collect()
+            })
+            .ok_or(ParseError::Malformed(i + 1))?;
+        let rect = Rectangle {
+            start: Point::new(captures[0], captures[1]),
+            end: Point::new(captures[2], captures[3]),
+        };
+
+        instructions.push(Instruction { kind, rect });
+    }
+    Ok(instructions)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{part_one, part_two};
+    use super::*;
 
     #[test]
     fn test_part_one() {
@@ -186,4 +366,132 @@ toggle 0,0 through 999,999
 ";
         assert_eq!(part_two(SAMPLE), 2000001);
     }
+
+    #[test]
+    fn test_grid_size() {
+        const SAMPLE: &str = "
+turn on 1,1 through 5,2
+toggle 0,0 through 2,999
+turn off 3,2 through 3,2
+";
+        // Rows 3.. and columns 4.. fall off the grid
+        assert_eq!(count_lit(SAMPLE, 4, 3, Behaviour::PartOne), 6);
+        assert_eq!(count_lit(SAMPLE, 4, 3, Behaviour::PartTwo), 23);
+        assert_eq!(count_lit(SAMPLE, 0, 0, Behaviour::PartTwo), 0);
+
+        // Backwards rectangles don't cover anything, and ones reaching the edge of `usize` don't
+        // overflow
+        assert_eq!(
+            count_lit("turn on 5,0 through 2,0", 10, 10, Behaviour::PartOne),
+            0
+        );
+        assert_eq!(
+            count_lit(
+                "turn on 8,9 through 18446744073709551615,9",
+                10,
+                10,
+                Behaviour::PartOne
+            ),
+            2
+        );
+    }
+
+    /// Each instruction drives its own colour channel.
+    struct Rgb;
+
+    impl LightBehaviour for Rgb {
+        type Light = [u8; 3];
+
+        fn turn_on(&self, light: &mut [u8; 3]) {
+            light[0] = 255;
+        }
+
+        fn turn_off(&self, light: &mut [u8; 3]) {
+            light[1] = 255;
+        }
+
+        fn toggle(&self, light: &mut [u8; 3]) {
+            light[2] ^= 255;
+        }
+
+        fn brightness(&self, light: &[u8; 3]) -> usize {
+            light.iter().map(|&c| c as usize).sum()
+        }
+
+        fn colour(&self, light: &[u8; 3], _: usize) -> [u8; 3] {
+            *light
+        }
+    }
+
+    #[test]
+    fn test_queries() {
+        const SAMPLE: &str = "
+turn on 0,0 through 3,3
+toggle 1,1 through 4,4
+turn off 0,0 through 0,0
+turn on 1,1 through 1,1
+";
+        let program = Program::parse(SAMPLE).unwrap();
+        assert_eq!(program.instructions().len(), 4);
+
+        let mut matrix = LightMatrix::new(5, 5, Behaviour::PartTwo);
+        program.run(&mut matrix);
+        assert_eq!(matrix.count_lit(), 15 + 32 + 1);
+        assert_eq!(
+            matrix.region_brightness(&Rectangle::new(Point::new(0, 0), Point::new(1, 1))),
+            2 + 4
+        );
+        assert_eq!(
+            matrix.region_brightness(&Rectangle::new(Point::new(3, 0), Point::new(1, 4))),
+            0
+        );
+        assert_eq!(
+            matrix.histogram(),
+            BTreeMap::from([(0, 3), (1, 6), (2, 7), (3, 8), (4, 1)])
+        );
+
+        assert_eq!(count_lit(SAMPLE, 5, 5, Dimmer { max: 2 }), 6 + 2 * 16);
+        let mut matrix = LightMatrix::new(5, 5, Dimmer { max: 2 });
+        program.run(&mut matrix);
+        assert_eq!(
+            matrix.histogram(),
+            BTreeMap::from([(0, 3), (1, 6), (2, 16)])
+        );
+
+        let mut matrix = LightMatrix::new(5, 5, Rgb);
+        program.run(&mut matrix);
+        assert_eq!(matrix.get(Point::new(0, 0)), Some(&[255, 255, 0]));
+        assert_eq!(matrix.get(Point::new(4, 4)), Some(&[0, 0, 255]));
+        assert_eq!(matrix.get(Point::new(5, 0)), None);
+
+        let image = matrix.to_image();
+        assert_eq!(image.dimensions(), (5, 5));
+        assert_eq!(image.get_pixel(2, 2).0, [255, 0, 255]);
+
+        let mut matrix = LightMatrix::new(2, 1, Behaviour::PartTwo);
+        Program::parse("turn on 0,0 through 1,0\ntoggle 1,0 through 1,0")
+            .unwrap()
+            .run(&mut matrix);
+        let image = matrix.to_image();
+        assert_eq!(image.get_pixel(0, 0).0, [85; 3]);
+        assert_eq!(image.get_pixel(1, 0).0, [255; 3]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (input, error) in [
+            (
+                "turn on 0,0 through 1,1\nflip 0,0 through 1,1",
+                ParseError::UnknownInstruction(2),
+            ),
+            ("\ntoggle 0,0 to 1,1", ParseError::Malformed(2)),
+            ("turn off 0,0 through 1", ParseError::Malformed(1)),
+            (
+                "turn on 0,0 through 99999999999999999999,1",
+                ParseError::Malformed(1),
+            ),
+        ] {
+            assert_eq!(Program::parse(input), Err(error));
+        }
+    }
 }
@@ -1,81 +1,110 @@
-#[derive(Debug)]
-enum State {
-    Closed,
-    Open,
-    Escaped,
-    Hex(usize),
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeErrorKind {
+    #[error("string must open with double quotes")]
+    MissingQuote,
+    #[error("string is never closed")]
+    Unterminated,
+    #[error("invalid escape sequence")]
+    InvalidEscape,
+    #[error("unexpected characters after closing quote")]
+    TrailingCharacters,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{kind} `{sequence}` at byte {offset}")]
+pub struct EscapeError {
+    pub kind: EscapeErrorKind,
+    pub offset: usize,
+    pub sequence: String,
 }
 
+/// Counts bytes rather than characters, to match what [`unescape`] decodes to.
 fn count_code_chars(s: &str) -> usize {
-    s.chars().count()
+    s.len()
 }
 
-fn count_memory_chars(s: &str) -> Result<usize, &'static str> {
-    let mut total = 0;
-    let mut state = State::Closed;
+/// Decodes a quoted string literal into the bytes it represents.
+pub fn unescape(s: &str) -> Result<Vec<u8>, EscapeError> {
+    let bytes = s.as_bytes();
+    let error = |kind, offset: usize, len: usize| EscapeError {
+        kind,
+        offset,
+        sequence: String::from_utf8_lossy(&bytes[offset..(offset + len).min(bytes.len())])
+            .into_owned(),
+    };
 
-    // println!("new str: {s}\n");
+    if bytes.first() != Some(&b'"') {
+        return Err(error(EscapeErrorKind::MissingQuote, 0, 1));
+    }
 
-    for c in s.chars() {
-        // println!("\tstate: {state:?}\n\ttotal: {total}\n\tnext: {c}\n");
-        match state {
-            State::Closed => {
-                if c == '"' {
-                    state = State::Open;
-                } else {
-                    return Err("string can only open with double quotes");
+    let mut decoded = Vec::new();
+    let mut i = 1;
+    loop {
+        match bytes.get(i) {
+            None => return Err(error(EscapeErrorKind::Unterminated, 0, bytes.len())),
+            Some(b'"') => break,
+            Some(b'\\') => match bytes.get(i + 1) {
+                Some(&c @ (b'\\' | b'"')) => {
+                    decoded.push(c);
+                    i += 2;
                 }
-            }
-            State::Open => match c {
-                '\\' => state = State::Escaped,
-                '"' => state = State::Closed,
-                _ => total += 1,
-            },
-            State::Escaped => match c {
-                '\\' | '"' => {
-                    total += 1;
-                    state = State::Open;
+                Some(b'x') => {
+                    // Exactly two hex digits
+                    let byte = bytes
+                        .get(i + 2..i + 4)
+                        .filter(|digits| digits.iter().all(u8::is_ascii_hexdigit))
+                        .and_then(|digits| std::str::from_utf8(digits).ok())
+                        .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                        .ok_or_else(|| error(EscapeErrorKind::InvalidEscape, i, 4))?;
+                    decoded.push(byte);
+                    i += 4;
                 }
-                'x' => state = State::Hex(0),
-                _ => return Err("invalid escape sequence"),
+                _ => return Err(error(EscapeErrorKind::InvalidEscape, i, 2)),
             },
-            State::Hex(mut i) => {
-                if c.is_ascii_hexdigit() {
-                    i += 1;
-                    // maximum of 2 hex characters
-                    if i >= 2 {
-                        total += 1;
-                        state = State::Open;
-                    } else {
-                        state = State::Hex(i);
-                    }
-                } else {
-                    return Err("invalid escape sequence");
-                }
+            Some(&c) => {
+                decoded.push(c);
+                i += 1;
             }
         }
     }
 
-    Ok(total)
+    let end = i + 1;
+    if end < bytes.len() {
+        return Err(error(
+            EscapeErrorKind::TrailingCharacters,
+            end,
+            bytes.len() - end,
+        ));
+    }
+    Ok(decoded)
 }
 
-fn escape(s: &str) -> String {
-    format!("{s:?}")
+/// Encodes a string as a quoted string literal, escaping quotes and backslashes with a
+/// backslash. Everything else is left as it is.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::from('"');
+    for c in s.chars() {
+        if matches!(c, '"' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped.push('"');
+    escaped
 }
 
 pub fn part_one(input: &'static str) -> usize {
     let input = parse_input(input);
-    let code_chars: usize = input.iter().map(|line| count_code_chars(&line)).sum();
-    let memory_chars: usize = input
-        .iter()
-        .map(|line| count_memory_chars(&line).unwrap())
-        .sum();
+    let code_chars: usize = input.iter().map(|line| count_code_chars(line)).sum();
+    let memory_chars: usize = input.iter().map(|line| unescape(line).unwrap().len()).sum();
     code_chars - memory_chars
 }
 
 pub fn part_two(input: &'static str) -> usize {
     let input = parse_input(input);
-    let code_chars: usize = input.iter().map(|line| count_code_chars(&line)).sum();
+    let code_chars: usize = input.iter().map(|line| count_code_chars(line)).sum();
     let escaped_chars: usize = input
         .iter()
         .map(|line| count_code_chars(&escape(line)))
@@ -95,7 +124,9 @@ fn parse_input(input: &'static str) -> Vec<&'static str> {
 
 #[cfg(test)]
 mod tests {
-    use super::{escape, part_one, part_two};
+    use proptest::prelude::*;
+
+    use super::*;
 
     const SAMPLE: &str = include_str!("../sample.txt");
 
@@ -117,8 +148,59 @@ mod tests {
             (r#""aaa\"aaa""#, r#""\"aaa\\\"aaa\"""#),
             (r#""\x27""#, r#""\"\\x27\"""#),
         ] {
-            println!("{}\n{}\n{}\n", input, escape(input), output);
             assert_eq!(escape(input), output);
         }
+        assert_eq!(escape("\0é\n"), "\"\0é\n\"");
+    }
+
+    #[test]
+    fn test_unescape() {
+        for (input, output) in [
+            (r#""""#, &b""[..]),
+            (r#""abc""#, b"abc"),
+            (r#""aaa\"aaa""#, b"aaa\"aaa"),
+            (r#""\x27\\""#, b"'\\"),
+            (r#""\xfF""#, b"\xff"),
+        ] {
+            assert_eq!(unescape(input).unwrap(), output);
+        }
+
+        for (input, kind, offset, sequence) in [
+            ("abc\"", EscapeErrorKind::MissingQuote, 0, "a"),
+            ("", EscapeErrorKind::MissingQuote, 0, ""),
+            (r#""abc"#, EscapeErrorKind::Unterminated, 0, r#""abc"#),
+            (r#""ab\n""#, EscapeErrorKind::InvalidEscape, 3, r#"\n"#),
+            (r#""ab\x4g""#, EscapeErrorKind::InvalidEscape, 3, r#"\x4g"#),
+            (r#""\x4"#, EscapeErrorKind::InvalidEscape, 1, r#"\x4"#),
+            (r#""abc"def"#, EscapeErrorKind::TrailingCharacters, 5, "def"),
+        ] {
+            let error = unescape(input).unwrap_err();
+            assert_eq!(
+                error,
+                EscapeError {
+                    kind,
+                    offset,
+                    sequence: sequence.to_string()
+                }
+            );
+        }
+        assert_eq!(
+            unescape(r#""ab\n""#).unwrap_err().to_string(),
+            r#"invalid escape sequence `\n` at byte 3"#
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(s in any::<String>()) {
+            prop_assert_eq!(unescape(&escape(&s)).unwrap(), s.as_bytes());
+        }
+
+        #[test]
+        fn test_round_trip_literal(s in r#""([a-z]|\\\\|\\"|\\x[0-9a-f]{2})*""#) {
+            // Escaping a literal and decoding it again gives back the literal's source
+            prop_assert!(unescape(&s).is_ok());
+            prop_assert_eq!(unescape(&escape(&s)).unwrap(), s.as_bytes());
+        }
     }
 }
@@ -170,13 +170,21 @@ pub mod intcode {
                             self.ip += n_args;
 
                             self.status = match opcode {
-                                Opcode::Add => {
-                                    memory[args[2]] = memory[args[0]] + memory[args[1]];
-                                    Status::Ready
-                                }
-                                Opcode::Mul => {
-                                    memory[args[2]] = memory[args[0]] * memory[args[1]];
-                                    Status::Ready
+                                Opcode::Add | Opcode::Mul => {
+                                    let result = memory
+                                        .get(args[0])
+                                        .zip(memory.get(args[1]))
+                                        .and_then(|(a, b)| match opcode {
+                                            Opcode::Add => a.checked_add(b),
+                                            _ => a.checked_mul(b),
+                                        });
+                                    match result {
+                                        Some(result) if args[2] < memory.len() => {
+                                            memory[args[2]] = result;
+                                            Status::Ready
+                                        }
+                                        _ => Status::Error,
+                                    }
                                 }
                                 Opcode::Hlt => Status::Finished,
                             }
@@ -225,6 +233,15 @@ pub mod intcode {
             }
         }
 
+        #[test]
+        fn test_errors() {
+            for program in ["1,0,0,9,99", "1,0,7,0,99", "1,0,0"] {
+                let memory = Memory::try_from(program).unwrap();
+                let mut interpreter = IntTerpreter::with_memory(memory);
+                assert_eq!(interpreter.execute(), Status::Error);
+            }
+        }
+
         fn run_program(raw_program: &str) -> Memory {
             let memory = Memory::try_from(raw_program).unwrap();
             let mut interpreter = IntTerpreter::with_memory(memory);
@@ -234,33 +251,195 @@ pub mod intcode {
     }
 }
 
+use std::ops::RangeInclusive;
+
 use intcode::*;
+use rayon::prelude::*;
+use thiserror::Error;
 
 pub fn part_one(input: &'static str) -> usize {
-    let mut memory = Memory::try_from(input).unwrap();
-    memory[1] = 12;
-    memory[2] = 2;
-    let mut interpreter = IntTerpreter::with_memory(memory);
-    interpreter.execute();
-    interpreter.get(0).unwrap()
+    let memory = Memory::try_from(input).unwrap();
+    run_with(&memory, 12, 2).unwrap()
 }
 
 pub fn part_two(input: &'static str) -> usize {
-    let base_memory = Memory::try_from(input).unwrap();
+    let memory = Memory::try_from(input).unwrap();
+    let (noun, verb) = Solver::new(&memory).solve(19690720).unwrap()[0];
+    100 * noun + verb
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    #[error("program is too short to have a noun and verb")]
+    TooShort,
+    #[error("no noun and verb give {0}")]
+    NoSolution(usize),
+}
+
+/// Runs the program with the given noun and verb, returning what's left at address 0 if it
+/// finishes cleanly.
+pub fn run_with(memory: &Memory, noun: usize, verb: usize) -> Option<usize> {
+    if memory.len() < 3 {
+        return None;
+    }
+    let mut memory = memory.clone();
+    memory[1] = noun;
+    memory[2] = verb;
+    let mut interpreter = IntTerpreter::with_memory(memory);
+    interpreter
+        .execute()
+        .is_finished()
+        .then(|| interpreter.get(0))
+        .flatten()
+}
+
+/// Searches for nouns and verbs that make a program output a target value.
+#[derive(Debug, Clone)]
+pub struct Solver<'a> {
+    memory: &'a Memory,
+    nouns: RangeInclusive<usize>,
+    verbs: RangeInclusive<usize>,
+    linear_probe: bool,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(memory: &'a Memory) -> Self {
+        Self {
+            memory,
+            nouns: 0..=99,
+            verbs: 0..=99,
+            linear_probe: false,
+        }
+    }
+
+    pub fn with_nouns(mut self, nouns: RangeInclusive<usize>) -> Self {
+        self.nouns = nouns;
+        self
+    }
 
-    const TARGET: usize = 19690720;
+    pub fn with_verbs(mut self, verbs: RangeInclusive<usize>) -> Self {
+        self.verbs = verbs;
+        self
+    }
+
+    /// Before searching the whole grid, checks whether the output looks like a linear function
+    /// of the noun and verb and, if it does, solves for the verb directly. Candidates are still
+    /// run to confirm them.
+    pub fn with_linear_probe(mut self) -> Self {
+        self.linear_probe = true;
+        self
+    }
 
-    for (noun, verb) in (0..128).flat_map(move |a| (0..128).map(move |b| (a, b))) {
-        let mut memory = base_memory.clone();
-        memory[1] = noun;
-        memory[2] = verb;
-        let mut interpreter = IntTerpreter::with_memory(memory);
-        interpreter.execute();
-        let output = interpreter.get(0).unwrap();
+    /// Returns every noun and verb that give `target`, ordered by noun and then verb.
+    pub fn solve(&self, target: usize) -> Result<Vec<(usize, usize)>, SolveError> {
+        if self.memory.len() < 3 {
+            return Err(SolveError::TooShort);
+        }
+        let pairs = self
+            .linear_probe
+            .then(|| self.solve_linear(target))
+            .flatten()
+            .unwrap_or_else(|| self.solve_grid(target));
+        if pairs.is_empty() {
+            Err(SolveError::NoSolution(target))
+        } else {
+            Ok(pairs)
+        }
+    }
 
-        if output == TARGET {
-            return 100 * noun + verb;
+    fn solve_grid(&self, target: usize) -> Vec<(usize, usize)> {
+        let verbs = self.verbs.clone();
+        self.nouns
+            .clone()
+            .into_par_iter()
+            .flat_map_iter(|noun| {
+                verbs
+                    .clone()
+                    .filter(move |&verb| run_with(self.memory, noun, verb) == Some(target))
+                    .map(move |verb| (noun, verb))
+            })
+            .collect()
+    }
+
+    /// Returns `None` if the probe runs show the output isn't linear.
+    fn solve_linear(&self, target: usize) -> Option<Vec<(usize, usize)>> {
+        let (n0, v0) = (*self.nouns.start(), *self.verbs.start());
+        let (n1, v1) = (*self.nouns.end(), *self.verbs.end());
+        if n1 <= n0 || v1 <= v0 {
+            return None;
+        }
+        let run = |noun, verb| run_with(self.memory, noun, verb).map(|output| output as i128);
+
+        let base = run(n0, v0)?;
+        let per_noun = run(n0 + 1, v0)? - base;
+        let per_verb = run(n0, v0 + 1)? - base;
+        let predict = |noun: usize, verb: usize| {
+            base + per_noun * (noun - n0) as i128 + per_verb * (verb - v0) as i128
+        };
+        for (noun, verb) in [(n0 + 1, v0 + 1), (n1, v0), (n0, v1), (n1, v1)] {
+            if run(noun, verb)? != predict(noun, verb) {
+                return None;
+            }
         }
+
+        let mut pairs = Vec::new();
+        for noun in self.nouns.clone() {
+            let remaining = target as i128 - predict(noun, v0);
+            let verbs = if per_verb == 0 {
+                if remaining == 0 {
+                    self.verbs.clone().collect()
+                } else {
+                    Vec::new()
+                }
+            } else if remaining % per_verb == 0 {
+                usize::try_from(v0 as i128 + remaining / per_verb)
+                    .ok()
+                    .filter(|verb| self.verbs.contains(verb))
+                    .into_iter()
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            pairs.extend(
+                verbs
+                    .into_iter()
+                    .filter(|&verb| run_with(self.memory, noun, verb) == Some(target))
+                    .map(|verb| (noun, verb)),
+            );
+        }
+        Some(pairs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solver() {
+        // Outputs 7 * (noun + verb), reading out of bounds once either gets past 13
+        let memory = Memory::try_from("1,0,0,3,1,1,2,3,2,3,13,0,99,7").unwrap();
+        let expected: Vec<_> = (0..=10).map(|noun| (noun, 10 - noun)).collect();
+        for solver in [
+            Solver::new(&memory).with_nouns(0..=20).with_verbs(0..=20),
+            Solver::new(&memory)
+                .with_nouns(0..=13)
+                .with_verbs(0..=13)
+                .with_linear_probe(),
+        ] {
+            assert_eq!(solver.solve(70), Ok(expected.clone()));
+            assert_eq!(solver.solve(71), Err(SolveError::NoSolution(71)));
+        }
+
+        // Outputs noun * verb, which the linear probe has to give up on
+        let memory = Memory::try_from("1,0,0,3,2,1,2,0,99").unwrap();
+        let solver = Solver::new(&memory)
+            .with_nouns(0..=8)
+            .with_verbs(0..=8)
+            .with_linear_probe();
+        assert_eq!(solver.solve(12), Ok(vec![(2, 6), (3, 4), (4, 3), (6, 2)]));
+
+        let memory = Memory::try_from("99").unwrap();
+        assert_eq!(Solver::new(&memory).solve(0), Err(SolveError::TooShort));
     }
-    panic!("Failed");
 }
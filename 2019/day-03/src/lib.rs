@@ -1,9 +1,11 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::RangeInclusive;
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-struct Pos {
-    x: isize,
-    y: isize,
+/// A point on the grid, with up being negative `y`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pos {
+    pub x: isize,
+    pub y: isize,
 }
 
 impl Pos {
@@ -19,7 +21,7 @@ impl Pos {
         ((self.x - other.x).abs() + (self.y - other.y).abs()) as usize
     }
 
-    pub fn len(&self) -> usize {
+    fn len(&self) -> usize {
         (self.x.abs() + self.y.abs()) as usize
     }
 
@@ -57,12 +59,6 @@ impl std::ops::AddAssign for Pos {
     }
 }
 
-#[derive(Debug)]
-struct Intersection {
-    pos: Pos,
-    steps: (usize, usize),
-}
-
 #[derive(Debug)]
 enum Line {
     Horizontal {
@@ -93,58 +89,300 @@ impl Line {
         })
     }
 
-    pub fn intersection(&self, other: &Line) -> Option<Intersection> {
+    /// Returns the number of steps along the wire to reach `pos`, which must be on the line.
+    pub fn steps_to(&self, pos: Pos) -> usize {
+        let index = match self {
+            Line::Horizontal { x, .. } => pos.x - x.start(),
+            Line::Vertical { y, .. } => pos.y - y.start(),
+        };
+        self.index_step(index as usize).unwrap()
+    }
+
+    /// Returns whether the line is horizontal, the coordinate it's fixed at and the range it
+    /// covers along the other axis.
+    fn span(&self) -> (bool, isize, &RangeInclusive<isize>) {
         match self {
-            Line::Horizontal { x: x_range, y, .. } => match other {
-                Line::Vertical { x, y: y_range, .. }
-                    if x_range.contains(x) && y_range.contains(y) =>
-                {
-                    Some(Intersection {
-                        pos: Pos { x: *x, y: *y },
-                        steps: (
-                            self.index_step((x - x_range.start()) as usize).unwrap(),
-                            other.index_step((y - y_range.start()) as usize).unwrap(),
-                        ),
-                    })
-                }
-                _ => None,
-            },
-            Line::Vertical { x, y: y_range, .. } => match other {
-                Line::Horizontal { x: x_range, y, .. }
-                    if x_range.contains(x) && y_range.contains(y) =>
-                {
-                    Some(Intersection {
-                        pos: Pos { x: *x, y: *y },
-                        steps: (
-                            self.index_step((y - y_range.start()) as usize).unwrap(),
-                            other.index_step((x - x_range.start()) as usize).unwrap(),
-                        ),
-                    })
-                }
-                _ => None,
-            },
+            Line::Horizontal { x, y, .. } => (true, *y, x),
+            Line::Vertical { x, y, .. } => (false, *x, y),
         }
     }
 }
 
-#[derive(Debug)]
-struct Wire {
-    lines: Vec<Line>,
+/// A point where wires cross at right angles, along with the fewest steps each wire takes to
+/// get there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crossing {
+    pos: Pos,
+    steps: BTreeMap<usize, usize>,
+}
+
+impl Crossing {
+    pub fn pos(&self) -> Pos {
+        self.pos
+    }
+
+    /// Returns the indices of the wires that cross here.
+    pub fn wires(&self) -> impl Iterator<Item = usize> + '_ {
+        self.steps.keys().copied()
+    }
+
+    /// Returns the fewest steps each wire takes to get here, by wire index.
+    pub fn steps(&self) -> &BTreeMap<usize, usize> {
+        &self.steps
+    }
+}
+
+/// A stretch where several wires run along the same line, with the steps each of them has taken
+/// at either end. A wire that passes through more than once has steps for every pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlap {
+    start: Pos,
+    end: Pos,
+    steps: BTreeMap<usize, Vec<(usize, usize)>>,
+}
+
+impl Overlap {
+    pub fn start(&self) -> Pos {
+        self.start
+    }
+
+    pub fn end(&self) -> Pos {
+        self.end
+    }
+
+    /// Returns the indices of the wires that run along here.
+    pub fn wires(&self) -> impl Iterator<Item = usize> + '_ {
+        self.steps.keys().copied()
+    }
+
+    /// Returns the steps each wire has taken at the start and end, for every time it passes
+    /// through, by wire index.
+    pub fn steps(&self) -> &BTreeMap<usize, Vec<(usize, usize)>> {
+        &self.steps
+    }
+
+    /// Returns every point of the overlap along with the fewest steps each wire takes to get
+    /// there.
+    pub fn points(&self) -> impl Iterator<Item = (Pos, BTreeMap<usize, usize>)> + '_ {
+        let direction = Pos {
+            x: (self.end.x - self.start.x).signum(),
+            y: (self.end.y - self.start.y).signum(),
+        };
+        let along = |&(start, end): &(usize, usize), i: usize| {
+            if start <= end {
+                start + i
+            } else {
+                start - i
+            }
+        };
+        (0..=self.start.manhattan_distance(&self.end)).map(move |i| {
+            let pos = Pos {
+                x: self.start.x + direction.x * i as isize,
+                y: self.start.y + direction.y * i as isize,
+            };
+            let steps = self
+                .steps
+                .iter()
+                .map(|(&wire, passes)| {
+                    (
+                        wire,
+                        passes.iter().map(|pass| along(pass, i)).min().unwrap(),
+                    )
+                })
+                .collect();
+            (pos, steps)
+        })
+    }
 }
 
-impl Wire {
-    pub fn intersections(&self, other: &Wire) -> Vec<Intersection> {
-        self.lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+    Insert,
+    Query,
+    Remove,
+}
+
+/// Everywhere that two or more wires meet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Intersections {
+    crossings: Vec<Crossing>,
+    overlaps: Vec<Overlap>,
+}
+
+impl Intersections {
+    pub fn new(wires: &[Wire]) -> Self {
+        let lines: Vec<(usize, &Line)> = wires
             .iter()
-            .flat_map(|line| {
-                other
-                    .lines
-                    .iter()
-                    .filter_map(|other_line| line.intersection(other_line))
-                    .filter(|inter| !inter.pos.is_zero())
-            })
+            .enumerate()
+            .flat_map(|(wire, w)| w.lines.iter().map(move |line| (wire, line)))
+            .collect();
+        Self {
+            crossings: Self::find_crossings(&lines),
+            overlaps: Self::find_overlaps(&lines),
+        }
+    }
+
+    /// Returns the points where wires cross at right angles, excluding the origin.
+    pub fn crossings(&self) -> &[Crossing] {
+        &self.crossings
+    }
+
+    /// Returns the stretches where wires run along each other.
+    pub fn overlaps(&self) -> &[Overlap] {
+        &self.overlaps
+    }
+
+    /// Sweeps from left to right, keeping track of the horizontal lines under the sweep so
+    /// each vertical line only looks at the ones it could cross.
+    fn find_crossings(lines: &[(usize, &Line)]) -> Vec<Crossing> {
+        let mut events = Vec::new();
+        for (i, &(_, line)) in lines.iter().enumerate() {
+            match line {
+                Line::Horizontal { x, .. } => {
+                    events.push((*x.start(), Event::Insert, i));
+                    events.push((*x.end(), Event::Remove, i));
+                }
+                Line::Vertical { x, .. } => events.push((*x, Event::Query, i)),
+            }
+        }
+        events.sort_unstable();
+
+        let mut active: BTreeMap<isize, Vec<usize>> = BTreeMap::new();
+        let mut found: BTreeMap<Pos, BTreeMap<usize, usize>> = BTreeMap::new();
+        for (x, event, i) in events {
+            let (wire, line) = lines[i];
+            match (event, line) {
+                (Event::Insert, Line::Horizontal { y, .. }) => {
+                    active.entry(*y).or_default().push(i)
+                }
+                (Event::Remove, Line::Horizontal { y, .. }) => {
+                    let row = active.get_mut(y).unwrap();
+                    row.retain(|&j| j != i);
+                    if row.is_empty() {
+                        active.remove(y);
+                    }
+                }
+                (Event::Query, Line::Vertical { y: ys, .. }) => {
+                    for (&y, row) in active.range(ys.clone()) {
+                        let pos = Pos { x, y };
+                        for (wire, line) in row.iter().map(|&j| lines[j]).chain([(wire, line)]) {
+                            let steps = line.steps_to(pos);
+                            let entry = found.entry(pos).or_default().entry(wire).or_insert(steps);
+                            *entry = (*entry).min(steps);
+                        }
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // Wires crossing themselves don't count, and neither does where they all start
+        found
+            .into_iter()
+            .filter(|(pos, steps)| steps.len() > 1 && !pos.is_zero())
+            .map(|(pos, steps)| Crossing { pos, steps })
             .collect()
     }
+
+    /// Sweeps along each row and column that has more than one line on it, splitting it up
+    /// wherever a line starts or stops. Each piece that's covered by more than one wire is an
+    /// overlap.
+    fn find_overlaps(lines: &[(usize, &Line)]) -> Vec<Overlap> {
+        let mut collinear: BTreeMap<(bool, isize), Vec<(usize, &Line)>> = BTreeMap::new();
+        for &(wire, line) in lines {
+            let (horizontal, fixed, _) = line.span();
+            collinear
+                .entry((horizontal, fixed))
+                .or_default()
+                .push((wire, line));
+        }
+
+        let mut overlaps = Vec::new();
+        for ((horizontal, fixed), group) in collinear {
+            let pos = |along: isize| {
+                if horizontal {
+                    Pos { x: along, y: fixed }
+                } else {
+                    Pos { x: fixed, y: along }
+                }
+            };
+
+            // Lines stop covering the point just past their end
+            let mut events = Vec::new();
+            for (i, (_, line)) in group.iter().enumerate() {
+                let range = line.span().2;
+                events.push((*range.start(), Event::Insert, i));
+                events.push((range.end() + 1, Event::Remove, i));
+            }
+            events.sort_unstable();
+
+            let mut active: Vec<usize> = Vec::new();
+            let mut events = events.into_iter().peekable();
+            while let Some(&(at, _, _)) = events.peek() {
+                while let Some((_, event, i)) = events.next_if(|&(other, _, _)| other == at) {
+                    match event {
+                        Event::Insert => active.push(i),
+                        _ => active.retain(|&j| j != i),
+                    }
+                }
+
+                // Everything up to the next event is covered by exactly the active lines
+                let Some(&(next, _, _)) = events.peek() else {
+                    break;
+                };
+                let wires: BTreeSet<usize> = active.iter().map(|&i| group[i].0).collect();
+                if wires.len() < 2 {
+                    continue;
+                }
+                let (start, end) = (pos(at), pos(next - 1));
+                let mut steps: BTreeMap<usize, Vec<(usize, usize)>> = BTreeMap::new();
+                for &(wire, line) in active.iter().map(|&i| &group[i]) {
+                    steps
+                        .entry(wire)
+                        .or_default()
+                        .push((line.steps_to(start), line.steps_to(end)));
+                }
+                overlaps.push(Overlap { start, end, steps });
+            }
+        }
+        overlaps
+    }
+
+    /// Returns every point other than the origin where wires meet, along with the fewest steps
+    /// each wire that meets there takes to get there. Points where crossings and overlaps
+    /// coincide are only returned once.
+    pub fn meetings(&self) -> BTreeMap<Pos, BTreeMap<usize, usize>> {
+        let crossings = self
+            .crossings
+            .iter()
+            .map(|crossing| (crossing.pos, crossing.steps.clone()));
+        let overlaps = self.overlaps.iter().flat_map(Overlap::points);
+
+        let mut points: BTreeMap<Pos, BTreeMap<usize, usize>> = BTreeMap::new();
+        for (pos, steps) in crossings.chain(overlaps) {
+            let point = points.entry(pos).or_default();
+            for (wire, steps) in steps {
+                let entry = point.entry(wire).or_insert(steps);
+                *entry = (*entry).min(steps);
+            }
+        }
+        points.retain(|pos, _| !pos.is_zero());
+        points
+    }
+
+    /// Returns every point other than the origin where wires meet, along with the combined
+    /// steps of the wires that meet there.
+    pub fn points(&self) -> impl Iterator<Item = (Pos, usize)> {
+        self.meetings()
+            .into_iter()
+            .map(|(pos, steps)| (pos, steps.values().sum()))
+    }
+}
+
+/// The lines a wire is made of, in the order it runs along them.
+#[derive(Debug)]
+pub struct Wire {
+    lines: Vec<Line>,
 }
 
 impl From<&str> for Wire {
@@ -205,33 +443,33 @@ fn abs_diff(a: usize, b: usize) -> usize {
 }
 
 pub fn part_one(input: &'static str) -> usize {
-    let wires = parse_input(input);
-    wires[0]
-        .intersections(&wires[1])
-        .iter()
-        .map(|inter| inter.pos.manhattan_distance(&Pos::zero()))
+    Intersections::new(&parse_input(input))
+        .points()
+        .map(|(pos, _)| pos.manhattan_distance(&Pos::zero()))
         .min()
         .unwrap()
 }
 
 pub fn part_two(input: &'static str) -> usize {
-    let wires = parse_input(input);
-    wires[0]
-        .intersections(&wires[1])
-        .iter()
-        .map(|inter| inter.steps.0 + inter.steps.1)
+    Intersections::new(&parse_input(input))
+        .points()
+        .map(|(_, steps)| steps)
         .min()
         .unwrap()
 }
 
-fn parse_input(input: &'static str) -> [Wire; 2] {
-    let mut wires = input.lines().map(Wire::from);
-    [wires.next().unwrap(), wires.next().unwrap()]
+fn parse_input(input: &'static str) -> Vec<Wire> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(Wire::from)
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{part_one, part_two};
+    use super::*;
 
     #[test]
     fn test_part_one() {
@@ -262,4 +500,114 @@ mod tests {
             410
         );
     }
+
+    #[test]
+    fn test_intersections() {
+        // A third wire crosses where the first two do, and runs along the second one twice
+        let wires = parse_input("R8,U5,L5,D3\nU7,R6,D4,L4\nU3,R8");
+        let intersections = Intersections::new(&wires);
+
+        let crossings: Vec<(Pos, Vec<usize>)> = intersections
+            .crossings()
+            .iter()
+            .map(|crossing| (crossing.pos(), crossing.wires().collect()))
+            .collect();
+        assert_eq!(
+            crossings,
+            [
+                (Pos { x: 0, y: -3 }, vec![1, 2]),
+                (Pos { x: 3, y: -3 }, vec![0, 1, 2]),
+                (Pos { x: 6, y: -5 }, vec![0, 1]),
+                (Pos { x: 6, y: -3 }, vec![1, 2]),
+                (Pos { x: 8, y: -3 }, vec![0, 2]),
+            ]
+        );
+        assert_eq!(
+            intersections.crossings[1].steps,
+            BTreeMap::from([(0, 20), (1, 20), (2, 6)])
+        );
+
+        assert_eq!(
+            intersections.overlaps,
+            [
+                Overlap {
+                    start: Pos { x: 0, y: -3 },
+                    end: Pos { x: 0, y: 0 },
+                    steps: BTreeMap::from([(1, vec![(3, 0)]), (2, vec![(3, 0)])]),
+                },
+                Overlap {
+                    start: Pos { x: 2, y: -3 },
+                    end: Pos { x: 6, y: -3 },
+                    steps: BTreeMap::from([(1, vec![(21, 17)]), (2, vec![(5, 9)])]),
+                },
+            ]
+        );
+        assert_eq!(
+            intersections.overlaps[1]
+                .points()
+                .map(|(pos, steps)| (pos, steps.values().sum()))
+                .collect::<Vec<_>>(),
+            (2..=6)
+                .map(|x| (Pos { x, y: -3 }, 26))
+                .collect::<Vec<(Pos, usize)>>()
+        );
+
+        // Crossings inside an overlap only show up once, counting every wire that meets there
+        let points: BTreeMap<Pos, usize> = intersections.points().collect();
+        assert_eq!(points.len(), intersections.points().count());
+        assert_eq!(points[&Pos { x: 3, y: -3 }], 20 + 20 + 6);
+        assert_eq!(points[&Pos { x: 4, y: -3 }], 26);
+        assert_eq!(
+            intersections.meetings()[&Pos { x: 3, y: -3 }],
+            BTreeMap::from([(0, 20), (1, 20), (2, 6)])
+        );
+        assert_eq!(
+            intersections.overlaps()[1].wires().collect::<Vec<_>>(),
+            [1, 2]
+        );
+
+        // The nearest point is on the first overlap, right next to the origin
+        assert_eq!(
+            intersections.points().min_by_key(|(pos, _)| pos.len()),
+            Some((Pos { x: 0, y: -1 }, 2))
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        // The first wire doubles back over itself, and all three share the start of the line
+        let wires = parse_input("R5,L5\nR3\nR4");
+        let intersections = Intersections::new(&wires);
+        assert_eq!(
+            intersections.overlaps,
+            [
+                Overlap {
+                    start: Pos { x: 0, y: 0 },
+                    end: Pos { x: 3, y: 0 },
+                    steps: BTreeMap::from([
+                        (0, vec![(0, 3), (10, 7)]),
+                        (1, vec![(0, 3)]),
+                        (2, vec![(0, 3)]),
+                    ]),
+                },
+                Overlap {
+                    start: Pos { x: 4, y: 0 },
+                    end: Pos { x: 4, y: 0 },
+                    steps: BTreeMap::from([(0, vec![(4, 4), (6, 6)]), (2, vec![(4, 4)])]),
+                },
+            ]
+        );
+
+        // Each wire counts the first time it gets there
+        assert_eq!(
+            intersections.points().collect::<Vec<_>>(),
+            [
+                (Pos { x: 1, y: 0 }, 3),
+                (Pos { x: 2, y: 0 }, 6),
+                (Pos { x: 3, y: 0 }, 9),
+                (Pos { x: 4, y: 0 }, 8),
+            ]
+        );
+        assert_eq!(part_two("R5,L5\nR3"), 2);
+    }
 }
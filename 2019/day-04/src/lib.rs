@@ -1,4 +1,4 @@
-use std::{borrow::Borrow, ops::RangeInclusive};
+use std::{borrow::Borrow, collections::HashMap, ops::RangeInclusive};
 
 pub fn runs<T: Eq>(seq: &[T]) -> Vec<usize> {
     let mut runs = Vec::new();
@@ -30,16 +30,164 @@ pub fn is_valid_part_two(pass: &usize) -> bool {
         && digits.windows(2).all(|win| win[0] <= win[1])
 }
 
+/// Which runs of repeated digits make a password valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// At least one digit repeated two or more times in a row.
+    Double,
+    /// At least one digit repeated exactly twice in a row.
+    ExactDouble,
+}
+
+impl Rule {
+    fn accepts(self, run: usize) -> bool {
+        match self {
+            Rule::Double => run >= 2,
+            Rule::ExactDouble => run == 2,
+        }
+    }
+}
+
+/// Longer runs all look the same to either rule, so they're only tracked up to this length.
+const MAX_RUN: usize = 3;
+
+/// Passwords with a fixed number of non-decreasing digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Passwords {
+    digits: usize,
+    rule: Rule,
+}
+
+impl Passwords {
+    pub fn new(digits: usize, rule: Rule) -> Self {
+        assert!(
+            (1..=19).contains(&digits),
+            "passwords must have between 1 and 19 digits"
+        );
+        Self { digits, rule }
+    }
+
+    pub fn is_valid(&self, pass: u64) -> bool {
+        let digits = to_digits(pass);
+        digits.len() == self.digits
+            && digits.windows(2).all(|win| win[0] <= win[1])
+            && runs(&digits).into_iter().any(|run| self.rule.accepts(run))
+    }
+
+    /// Counts the valid passwords in `range` without visiting each of them.
+    pub fn count(&self, range: RangeInclusive<u64>) -> u64 {
+        let below = match range.start().checked_sub(1) {
+            Some(start) => self.count_up_to(start),
+            None => 0,
+        };
+        self.count_up_to(*range.end()).saturating_sub(below)
+    }
+
+    /// Counts the valid passwords no greater than `n`, by walking down its digits and counting
+    /// every password which first goes below `n` at each position.
+    fn count_up_to(&self, n: u64) -> u64 {
+        let mut memo = HashMap::new();
+        let digits = to_digits(n.min(10u64.pow(self.digits as u32) - 1));
+        if digits.len() < self.digits {
+            return 0;
+        }
+
+        let mut total = 0;
+        let mut last = 1;
+        let mut run = 0;
+        let mut satisfied = false;
+        for (i, &limit) in digits.iter().enumerate() {
+            let remaining = self.digits - i - 1;
+            for digit in last..limit {
+                let (next_run, next_satisfied) = self.push(digit, last, run, satisfied);
+                total += self.completions(&mut memo, remaining, digit, next_run, next_satisfied);
+            }
+            if limit < last {
+                return total;
+            }
+            (run, satisfied) = self.push(limit, last, run, satisfied);
+            last = limit;
+        }
+        // `n` itself
+        total + u64::from(satisfied || self.rule.accepts(run))
+    }
+
+    /// Returns the run length and whether a valid run has been seen after appending `digit`.
+    fn push(&self, digit: u8, last: u8, run: usize, satisfied: bool) -> (usize, bool) {
+        if digit == last && run > 0 {
+            ((run + 1).min(MAX_RUN), satisfied)
+        } else {
+            (1, satisfied || self.rule.accepts(run))
+        }
+    }
+
+    /// Counts the ways of adding `remaining` more digits to make a valid password.
+    fn completions(
+        &self,
+        memo: &mut HashMap<(usize, u8, usize, bool), u64>,
+        remaining: usize,
+        last: u8,
+        run: usize,
+        satisfied: bool,
+    ) -> u64 {
+        if remaining == 0 {
+            return u64::from(satisfied || self.rule.accepts(run));
+        }
+        if let Some(&count) = memo.get(&(remaining, last, run, satisfied)) {
+            return count;
+        }
+        let count = (last..=9)
+            .map(|digit| {
+                let (run, satisfied) = self.push(digit, last, run, satisfied);
+                self.completions(memo, remaining - 1, digit, run, satisfied)
+            })
+            .sum();
+        memo.insert((remaining, last, run, satisfied), count);
+        count
+    }
+
+    /// Lazily lists the valid passwords in `range` in ascending order, skipping straight past
+    /// any numbers whose digits decrease.
+    pub fn iter(&self, range: RangeInclusive<u64>) -> impl Iterator<Item = u64> {
+        let passwords = *self;
+        let smallest = 10u64.pow(self.digits as u32 - 1);
+        let largest = 10u64.pow(self.digits as u32) - 1;
+        let end = (*range.end()).min(largest);
+        std::iter::successors(
+            next_non_decreasing((*range.start()).max(smallest)),
+            |&pass| pass.checked_add(1).and_then(next_non_decreasing),
+        )
+        .take_while(move |&pass| pass <= end)
+        .filter(move |&pass| passwords.is_valid(pass))
+    }
+}
+
+fn to_digits(n: u64) -> Vec<u8> {
+    n.to_string().bytes().map(|b| b - b'0').collect()
+}
+
+/// Returns the smallest number at least `n` whose digits never decrease, or `None` if it doesn't
+/// fit in a `u64`.
+fn next_non_decreasing(n: u64) -> Option<u64> {
+    let mut digits = to_digits(n);
+    if let Some(i) = (1..digits.len()).find(|&i| digits[i] < digits[i - 1]) {
+        let fill = digits[i - 1];
+        digits[i..].iter_mut().for_each(|digit| *digit = fill);
+    }
+    digits.into_iter().try_fold(0u64, |n, digit| {
+        n.checked_mul(10)?.checked_add(u64::from(digit))
+    })
+}
+
 pub fn part_one(input: &'static str) -> usize {
-    parse_input(input).filter(is_valid).count()
+    Passwords::new(6, Rule::Double).count(parse_input(input)) as usize
 }
 
 pub fn part_two(input: &'static str) -> usize {
-    // Incorrect: 535
-    parse_input(input).filter(is_valid_part_two).count()
+    Passwords::new(6, Rule::ExactDouble).count(parse_input(input)) as usize
 }
 
-fn parse_input(input: &'static str) -> RangeInclusive<usize> {
+fn parse_input(input: &'static str) -> RangeInclusive<u64> {
     if let [start, end] = input
         .trim()
         .split('-')
@@ -54,7 +202,9 @@ fn parse_input(input: &'static str) -> RangeInclusive<usize> {
 
 #[cfg(test)]
 mod tests {
-    use super::{is_valid, is_valid_part_two};
+    use proptest::prelude::*;
+
+    use super::*;
 
     #[test]
     fn test_part_one() {
@@ -76,4 +226,53 @@ mod tests {
         assert_eq!(is_valid_part_two(&123444), false);
         assert_eq!(is_valid_part_two(&111122), true);
     }
+
+    #[test]
+    fn test_count() {
+        for digits in 1..=5 {
+            let range = 0..=10u64.pow(digits as u32);
+            for rule in [Rule::Double, Rule::ExactDouble] {
+                let passwords = Passwords::new(digits, rule);
+                let expected: Vec<u64> = range
+                    .clone()
+                    .filter(|&pass| passwords.is_valid(pass))
+                    .collect();
+                assert_eq!(passwords.count(range.clone()), expected.len() as u64);
+                assert_eq!(passwords.iter(range.clone()).collect::<Vec<_>>(), expected);
+            }
+        }
+
+        let passwords = Passwords::new(19, Rule::ExactDouble);
+        assert_eq!(
+            passwords.count(0..=u64::MAX),
+            passwords.count(0..=9_999_999_999_999_999_999)
+        );
+        assert_eq!(
+            passwords.iter(0..=u64::MAX).next(),
+            Some(1_111_111_111_111_111_122)
+        );
+        assert_eq!(passwords.count(RangeInclusive::new(5, 4)), 0);
+        assert_eq!(
+            passwords.iter(18_400_000_000_000_000_000..=u64::MAX).next(),
+            None
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_random_ranges(start in 0..1_100_000u64, len in 0..5_000u64) {
+            let range = start..=start + len;
+            let part_one = Passwords::new(6, Rule::Double);
+            let part_two = Passwords::new(6, Rule::ExactDouble);
+            let expected_one = range.clone().filter(|&pass| is_valid(&(pass as usize))).count();
+            let expected_two = range
+                .clone()
+                .filter(|&pass| is_valid_part_two(&(pass as usize)))
+                .count();
+            prop_assert_eq!(part_one.count(range.clone()), expected_one as u64);
+            prop_assert_eq!(part_two.count(range.clone()), expected_two as u64);
+            prop_assert_eq!(part_one.iter(range.clone()).count(), expected_one);
+            prop_assert_eq!(part_two.iter(range).count(), expected_two);
+        }
+    }
 }
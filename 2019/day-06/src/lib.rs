@@ -1,74 +1,264 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
-type OrbitMap = HashMap<&'static str, &'static str>;
+use thiserror::Error;
 
-#[derive(Default, Clone, Copy)]
-struct OrbitCount {
-    direct: usize,
-    indirect: usize,
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OrbitError {
+    #[error("there aren't any orbits")]
+    Empty,
+    #[error("line {0} isn't of the form `A)B`")]
+    Malformed(usize),
+    #[error("{object} orbits both {first} and {second}")]
+    TwoParents {
+        object: &'static str,
+        first: &'static str,
+        second: &'static str,
+    },
+    #[error("there's more than one centre of mass: {}", .0.join(", "))]
+    MultipleRoots(Vec<&'static str>),
+    #[error("orbits go round in a circle: {}", .0.join(" -> "))]
+    Cycle(Vec<&'static str>),
 }
 
-impl OrbitCount {
-    pub fn total(&self) -> usize {
-        self.direct + self.indirect
-    }
+/// Every object and what it orbits, checked to form a single tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrbitTree {
+    names: Vec<&'static str>,
+    index: HashMap<&'static str, usize>,
+    parents: Vec<Option<usize>>,
+    depths: Vec<usize>,
+    subtree_sizes: Vec<usize>,
 }
 
-fn count_orbits(orbits: &OrbitMap) -> OrbitCount {
-    let mut count = OrbitCount::default();
-    for mut parent in orbits.values() {
-        count.direct += 1;
-        while let Some(grandparent) = orbits.get(parent) {
-            count.indirect += 1;
-            parent = grandparent;
+impl OrbitTree {
+    /// Parses one `A)B` orbit per line, meaning `B` orbits `A`.
+    pub fn parse(input: &'static str) -> Result<Self, OrbitError> {
+        let mut names = Vec::new();
+        let mut index = HashMap::new();
+        let mut parents: Vec<Option<usize>> = Vec::new();
+        let mut id = |name: &'static str, parents: &mut Vec<Option<usize>>| {
+            *index.entry(name).or_insert_with(|| {
+                names.push(name);
+                parents.push(None);
+                parents.len() - 1
+            })
+        };
+
+        for (i, line) in input.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (parent, child) = line.split_once(')').ok_or(OrbitError::Malformed(i + 1))?;
+            let (parent, child) = (parent.trim(), child.trim());
+            if parent.is_empty() || child.is_empty() {
+                return Err(OrbitError::Malformed(i + 1));
+            }
+            let (p, c) = (id(parent, &mut parents), id(child, &mut parents));
+            match parents[c] {
+                Some(existing) if existing != p => {
+                    return Err(OrbitError::TwoParents {
+                        object: child,
+                        first: names[existing],
+                        second: parent,
+                    })
+                }
+                _ => parents[c] = Some(p),
+            }
+        }
+
+        let mut roots: Vec<usize> = (0..names.len()).filter(|&i| parents[i].is_none()).collect();
+        if roots.len() > 1 {
+            let mut roots: Vec<&str> = roots.into_iter().map(|i| names[i]).collect();
+            roots.sort_unstable();
+            return Err(OrbitError::MultipleRoots(roots));
+        }
+
+        let mut children = vec![Vec::new(); names.len()];
+        for (child, parent) in parents.iter().enumerate() {
+            if let Some(parent) = parent {
+                children[*parent].push(child);
+            }
+        }
+
+        // Work out every depth in one pass down from the root
+        let mut depths = vec![0; names.len()];
+        let mut order = Vec::with_capacity(names.len());
+        let mut queue: VecDeque<usize> = roots.drain(..).collect();
+        while let Some(object) = queue.pop_front() {
+            order.push(object);
+            for &child in &children[object] {
+                depths[child] = depths[object] + 1;
+                queue.push_back(child);
+            }
         }
+
+        // Anything the root can't reach has to be going round in circles
+        if order.len() < names.len() {
+            let mut reached = vec![false; names.len()];
+            order.iter().for_each(|&i| reached[i] = true);
+            let start = (0..names.len())
+                .filter(|&i| !reached[i])
+                .min_by_key(|&i| names[i])
+                .unwrap();
+            let mut path = vec![start];
+            let mut object = parents[start].unwrap();
+            while !path.contains(&object) {
+                path.push(object);
+                object = parents[object].unwrap();
+            }
+            let cycle = path.iter().position(|&i| i == object).unwrap();
+            let mut cycle: Vec<&str> = path[cycle..].iter().map(|&i| names[i]).collect();
+            cycle.push(names[object]);
+            return Err(OrbitError::Cycle(cycle));
+        }
+        if names.is_empty() {
+            return Err(OrbitError::Empty);
+        }
+
+        let mut subtree_sizes = vec![1; names.len()];
+        for &object in order.iter().rev() {
+            if let Some(parent) = parents[object] {
+                subtree_sizes[parent] += subtree_sizes[object];
+            }
+        }
+
+        Ok(Self {
+            names,
+            index,
+            parents,
+            depths,
+            subtree_sizes,
+        })
     }
-    count
-}
 
-fn get_address(orbits: &OrbitMap, start: &str) -> Vec<&'static str> {
-    let mut address = Vec::new();
-    if let Some(&parent) = orbits.get(start) {
-        address.push(parent);
-        address.extend(get_address(orbits, parent));
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns the object which everything else orbits.
+    pub fn root(&self) -> &'static str {
+        self.names[self.depths.iter().position(|&depth| depth == 0).unwrap()]
+    }
+
+    pub fn parent(&self, object: &str) -> Option<&'static str> {
+        self.parents[*self.index.get(object)?].map(|parent| self.names[parent])
+    }
+
+    /// Returns how many objects `object` orbits, directly and indirectly.
+    pub fn depth(&self, object: &str) -> Option<usize> {
+        self.index.get(object).map(|&i| self.depths[i])
     }
-    address
-}
 
-fn plot_transfer(orbits: &OrbitMap) -> Vec<&'static str> {
-    let mut start_address = get_address(orbits, "YOU");
-    let mut end_address = get_address(orbits, "SAN");
-    while start_address.last() == end_address.last() {
-        start_address.pop();
-        end_address.pop();
+    /// Returns how many objects orbit `object`, counting itself.
+    pub fn subtree_size(&self, object: &str) -> Option<usize> {
+        self.index.get(object).map(|&i| self.subtree_sizes[i])
+    }
+
+    pub fn total_orbits(&self) -> usize {
+        self.depths.iter().sum()
+    }
+
+    fn lowest_common_ancestor(&self, mut a: usize, mut b: usize) -> usize {
+        while self.depths[a] > self.depths[b] {
+            a = self.parents[a].unwrap();
+        }
+        while self.depths[b] > self.depths[a] {
+            b = self.parents[b].unwrap();
+        }
+        while a != b {
+            a = self.parents[a].unwrap();
+            b = self.parents[b].unwrap();
+        }
+        a
+    }
+
+    /// Returns the closest object which both `a` and `b` orbit, or are.
+    pub fn common_ancestor(&self, a: &str, b: &str) -> Option<&'static str> {
+        let (&a, &b) = (self.index.get(a)?, self.index.get(b)?);
+        Some(self.names[self.lowest_common_ancestor(a, b)])
+    }
+
+    /// Returns the objects on the way from `a` to `b` through their common ancestor, including
+    /// both ends.
+    fn path_between(&self, a: usize, b: usize) -> Vec<usize> {
+        let ancestor = self.lowest_common_ancestor(a, b);
+        let climb = |mut object: usize| {
+            let mut path = vec![object];
+            while object != ancestor {
+                object = self.parents[object].unwrap();
+                path.push(object);
+            }
+            path
+        };
+        let mut path = climb(a);
+        let mut descent = climb(b);
+        descent.pop();
+        path.extend(descent.into_iter().rev());
+        path
+    }
+
+    fn distance_between(&self, a: usize, b: usize) -> usize {
+        let ancestor = self.lowest_common_ancestor(a, b);
+        self.depths[a] + self.depths[b] - 2 * self.depths[ancestor]
+    }
+
+    /// Returns the objects passed through going from `a` to `b`, including both.
+    pub fn path(&self, a: &str, b: &str) -> Option<Vec<&'static str>> {
+        let (&a, &b) = (self.index.get(a)?, self.index.get(b)?);
+        Some(
+            self.path_between(a, b)
+                .into_iter()
+                .map(|i| self.names[i])
+                .collect(),
+        )
+    }
+
+    /// Returns the number of orbits between `a` and `b`.
+    pub fn distance(&self, a: &str, b: &str) -> Option<usize> {
+        let (&a, &b) = (self.index.get(a)?, self.index.get(b)?);
+        Some(self.distance_between(a, b))
+    }
+
+    /// Returns the objects passed through moving from what `from` orbits to what `to` orbits.
+    /// Either of them not orbiting anything gives `None`.
+    pub fn transfer_path(&self, from: &str, to: &str) -> Option<Vec<&'static str>> {
+        let from = self.parents[*self.index.get(from)?]?;
+        let to = self.parents[*self.index.get(to)?]?;
+        Some(
+            self.path_between(from, to)
+                .into_iter()
+                .map(|i| self.names[i])
+                .collect(),
+        )
+    }
+
+    /// Returns the number of orbital transfers needed to get `from` into orbit with `to`.
+    /// Either of them not orbiting anything gives `None`.
+    pub fn transfers(&self, from: &str, to: &str) -> Option<usize> {
+        let from = self.parents[*self.index.get(from)?]?;
+        let to = self.parents[*self.index.get(to)?]?;
+        Some(self.distance_between(from, to))
     }
-    start_address.extend(end_address.iter().rev());
-    start_address
 }
 
 pub fn part_one(input: &'static str) -> usize {
-    let orbits = parse_input(input);
-    count_orbits(&orbits).total()
+    OrbitTree::parse(input).unwrap().total_orbits()
 }
 
 pub fn part_two(input: &'static str) -> usize {
-    let orbits = parse_input(input);
-    plot_transfer(&orbits).len()
-}
-
-fn parse_input(input: &'static str) -> OrbitMap {
-    input
-        .lines()
-        .map(|line| {
-            let (parent, child) = line.split_once(')').unwrap();
-            (child, parent)
-        })
-        .collect()
+    OrbitTree::parse(input)
+        .unwrap()
+        .transfers("YOU", "SAN")
+        .unwrap()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{part_one, part_two};
+    use super::*;
 
     const SAMPLE_1: &str = include_str!("../sample_1.txt");
     const SAMPLE_2: &str = include_str!("../sample_2.txt");
@@ -82,4 +272,71 @@ mod tests {
     fn test_part_two() {
         assert_eq!(part_two(SAMPLE_2), 4);
     }
+
+    #[test]
+    fn test_tree() {
+        let tree = OrbitTree::parse(SAMPLE_2).unwrap();
+        assert_eq!(tree.len(), 14);
+        assert_eq!(tree.root(), "COM");
+        assert_eq!(tree.parent("COM"), None);
+        assert_eq!(tree.parent("SAN"), Some("I"));
+        assert_eq!(tree.depth("YOU"), Some(7));
+        assert_eq!(tree.depth("Pluto"), None);
+        assert_eq!(tree.subtree_size("COM"), Some(14));
+        assert_eq!(tree.subtree_size("E"), Some(6));
+        assert_eq!(tree.common_ancestor("YOU", "SAN"), Some("D"));
+        assert_eq!(tree.common_ancestor("L", "K"), Some("K"));
+        assert_eq!(
+            tree.transfer_path("YOU", "SAN").unwrap(),
+            ["K", "J", "E", "D", "I"]
+        );
+        assert_eq!(
+            tree.transfer_path("H", "F").unwrap(),
+            ["G", "B", "C", "D", "E"]
+        );
+        assert_eq!(tree.transfers("SAN", "YOU"), Some(4));
+        assert_eq!(tree.transfers("COM", "YOU"), None);
+
+        assert_eq!(tree.distance("COM", "YOU"), Some(7));
+        assert_eq!(tree.distance("H", "F"), Some(6));
+        assert_eq!(tree.distance("SAN", "SAN"), Some(0));
+        assert_eq!(tree.distance("SAN", "Pluto"), None);
+        assert_eq!(
+            tree.path("COM", "YOU").unwrap(),
+            ["COM", "B", "C", "D", "E", "J", "K", "YOU"]
+        );
+        assert_eq!(tree.path("L", "K").unwrap(), ["L", "K"]);
+        assert_eq!(tree.path("SAN", "SAN").unwrap(), ["SAN"]);
+    }
+
+    #[test]
+    fn test_validation() {
+        for (input, error) in [
+            ("", OrbitError::Empty),
+            ("COM)B\nB-C", OrbitError::Malformed(2)),
+            (
+                "COM)B\nCOM)C\nB)D\nC)D",
+                OrbitError::TwoParents {
+                    object: "D",
+                    first: "B",
+                    second: "C",
+                },
+            ),
+            (
+                "COM)B\nX)Y\nA)C",
+                OrbitError::MultipleRoots(vec!["A", "COM", "X"]),
+            ),
+            (
+                "COM)B\nD)C\nC)E\nE)D",
+                OrbitError::Cycle(vec!["C", "D", "E", "C"]),
+            ),
+            ("A)A", OrbitError::Cycle(vec!["A", "A"])),
+        ] {
+            assert_eq!(OrbitTree::parse(input), Err(error));
+        }
+        assert_eq!(
+            OrbitError::Cycle(vec!["C", "D", "C"]).to_string(),
+            "orbits go round in a circle: C -> D -> C"
+        );
+    }
 }
@@ -0,0 +1,167 @@
+use aoc_2019_day_07::intterpreter::{
+    Instruction, Int, IntTerpreter, Memory, Opcode, ParameterMode,
+};
+use aoc_2019_day_07::{part_one, part_two};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use itertools::Itertools;
+
+/// Day 5's example of comparing the input against 8.
+const DAY_05_SAMPLE: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+/// Day 7's third part one example, which halts after one signal so it works in a feedback loop
+/// too.
+const DAY_07_SAMPLE: &str = "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0";
+
+/// Puzzle inputs aren't checked in, so fall back to an example program when there isn't one.
+fn load(path: &str, sample: &'static str) -> &'static str {
+    match std::fs::read_to_string(path) {
+        Ok(input) if !input.trim().is_empty() => Box::leak(input.into_boxed_str()),
+        _ => {
+            eprintln!(
+                "no puzzle input at {}, benchmarking the example instead",
+                path
+            );
+            sample
+        }
+    }
+}
+
+/// Decodes a word the way the interpreter does.
+type Decoder = fn(Int) -> Result<Instruction, ()>;
+
+/// The original decoder, which pads the word out to a string. Kept as a baseline for `decode`.
+fn decode_formatted(value: Int) -> Result<Instruction, ()> {
+    let value = format!("{:05}", value);
+    let (modes, opcode) = value.split_at(3);
+    let modes: Vec<ParameterMode> = modes
+        .chars()
+        .map(|c| ParameterMode::try_from(Int::new(c.to_digit(10).unwrap() as i64)).unwrap())
+        .rev()
+        .collect();
+    let opcode = Opcode::try_from(opcode.parse::<Int>().unwrap())?;
+    Ok(Instruction::new(opcode, modes[..3].try_into().unwrap()))
+}
+
+/// A bare bones interpreter for days 5 and 7, so that whole programs can be run with either
+/// decoder. Returns the last output.
+fn run(memory: &[i64], input: &[i64], decode: Decoder) -> i64 {
+    let mut memory = memory.to_vec();
+    let mut input = input.iter().copied();
+    let mut output = 0;
+    let mut ip = 0;
+    loop {
+        let instruction = decode(Int::new(memory[ip])).unwrap();
+        let args: Vec<usize> = (0..instruction.arg_len())
+            .map(|i| match instruction.modes()[i] {
+                ParameterMode::Immediate => ip + 1 + i,
+                _ => memory[ip + 1 + i] as usize,
+            })
+            .collect();
+        ip += 1 + args.len();
+        match instruction.opcode() {
+            Opcode::Add => memory[args[2]] = memory[args[0]] + memory[args[1]],
+            Opcode::Mul => memory[args[2]] = memory[args[0]] * memory[args[1]],
+            Opcode::Str => memory[args[0]] = input.next().unwrap(),
+            Opcode::Out => output = memory[args[0]],
+            Opcode::Bot if memory[args[0]] != 0 => ip = memory[args[1]] as usize,
+            Opcode::Bof if memory[args[0]] == 0 => ip = memory[args[1]] as usize,
+            Opcode::Bot | Opcode::Bof => {}
+            Opcode::Clt => memory[args[2]] = (memory[args[0]] < memory[args[1]]) as i64,
+            Opcode::Ceq => memory[args[2]] = (memory[args[0]] == memory[args[1]]) as i64,
+            _ => return output,
+        }
+    }
+}
+
+/// Day 7 part one on the bare bones interpreter.
+fn amplify_all(memory: &[i64], decode: Decoder) -> i64 {
+    (0..5)
+        .permutations(5)
+        .map(|sequence| {
+            sequence.into_iter().fold(0, |signal, phase_setting| {
+                run(memory, &[phase_setting, signal], decode)
+            })
+        })
+        .max()
+        .unwrap()
+}
+
+fn words(input: &str) -> Vec<i64> {
+    Vec::from(Memory::<Int>::try_from(input).unwrap())
+        .into_iter()
+        .map(i64::from)
+        .collect()
+}
+
+const DECODERS: [(&str, Decoder); 2] = [
+    (" (formatted decode)", decode_formatted),
+    (" (decode)", Instruction::try_from),
+];
+
+fn decode(c: &mut Criterion) {
+    let words: Vec<Int> = (0..100_000)
+        .map(Int::new)
+        .filter(|&word| Instruction::try_from(word).is_ok())
+        .collect();
+    for (suffix, decode) in DECODERS {
+        c.bench_function(&format!("decode{}", suffix), |b| {
+            b.iter(|| {
+                for &word in &words {
+                    black_box(decode(black_box(word))).unwrap();
+                }
+            })
+        });
+    }
+}
+
+fn day_05(c: &mut Criterion) {
+    let input = load(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../day-05/input.txt"),
+        DAY_05_SAMPLE,
+    );
+    let memory: Memory = Memory::try_from(input).unwrap();
+    let words = words(input);
+    for (name, system_id) in [("day 05 part one", 1), ("day 05 part two", 5)] {
+        for (suffix, cache) in [("", false), (" (decode cache)", true)] {
+            c.bench_function(&format!("{}{}", name, suffix), |b| {
+                b.iter(|| {
+                    let interpreter = IntTerpreter::new()
+                        .with_memory(memory.clone())
+                        .with_input(&[system_id])
+                        .with_buffer();
+                    let mut interpreter = if cache {
+                        interpreter.with_decode_cache()
+                    } else {
+                        interpreter
+                    };
+                    interpreter.execute();
+                    interpreter.output().unwrap().last().copied()
+                })
+            });
+        }
+        for (suffix, decode) in DECODERS {
+            c.bench_function(&format!("{}{}", name, suffix), |b| {
+                b.iter(|| run(&words, &[system_id], decode))
+            });
+        }
+    }
+}
+
+fn day_07(c: &mut Criterion) {
+    let input = load(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/input.txt"),
+        DAY_07_SAMPLE,
+    );
+    c.bench_function("day 07 part one", |b| b.iter(|| part_one(input)));
+    c.bench_function("day 07 part two", |b| b.iter(|| part_two(input)));
+
+    let words = words(input);
+    for (suffix, decode) in DECODERS {
+        c.bench_function(&format!("day 07 part one{}", suffix), |b| {
+            b.iter(|| amplify_all(&words, decode))
+        });
+    }
+}
+
+criterion_group!(benches, decode, day_05, day_07);
+criterion_main!(benches);
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::intterpreter::{Instruction, Int, Memory, Opcode, ParameterMode};
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("line {line}: {kind}")]
+pub struct AssembleError {
+    pub line: usize,
+    pub kind: AssembleErrorKind,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AssembleErrorKind {
+    #[error("unknown mnemonic `{0}`")]
+    UnknownMnemonic(String),
+    #[error("`{0}` takes {1} operands but {2} were given")]
+    OperandCount(Opcode, usize, usize),
+    #[error("invalid operand `{0}`")]
+    InvalidOperand(String),
+    #[error("invalid label name `{0}`")]
+    InvalidLabel(String),
+    #[error("label `{0}` is defined more than once")]
+    DuplicateLabel(String),
+    #[error("undefined label `{0}`")]
+    UndefinedLabel(String),
+    #[error("listed address {0} doesn't match the actual address {1}")]
+    AddressMismatch(usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Literal(Int),
+    Label(String),
+}
+
+impl std::str::FromStr for Value {
+    type Err = AssembleErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(value) = s.parse() {
+            Ok(Self::Literal(value))
+        } else if is_identifier(s) {
+            Ok(Self::Label(s.to_string()))
+        } else {
+            Err(AssembleErrorKind::InvalidOperand(s.to_string()))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Statement {
+    Instruction(Instruction, Vec<Value>),
+    Data(Vec<Value>),
+}
+
+impl Statement {
+    fn len(&self) -> usize {
+        match self {
+            Self::Instruction(_, args) => args.len() + 1,
+            Self::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_operand(s: &str) -> Result<(ParameterMode, Value), AssembleErrorKind> {
+    let invalid = || AssembleErrorKind::InvalidOperand(s.to_string());
+    if let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Ok((ParameterMode::Position, inner.trim().parse()?))
+    } else if let Some(value) = s.strip_prefix('#') {
+        Ok((ParameterMode::Immediate, value.trim().parse()?))
+    } else if let Some(offset) = s.strip_prefix("rb") {
+        let offset = offset.trim();
+        let offset = match offset.strip_prefix('+') {
+            _ if offset.is_empty() => Int::new(0),
+            Some(offset) => offset.trim().parse().map_err(|_| invalid())?,
+            None => offset.parse().map_err(|_| invalid())?,
+        };
+        Ok((ParameterMode::Relative, Value::Literal(offset)))
+    } else {
+        Err(invalid())
+    }
+}
+
+fn parse_statement(s: &str) -> Result<Statement, AssembleErrorKind> {
+    let (mnemonic, operands) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+    let operands: Vec<&str> = if operands.trim().is_empty() {
+        Vec::new()
+    } else {
+        operands.split(',').map(str::trim).collect()
+    };
+
+    if mnemonic == ".data" {
+        return Ok(Statement::Data(
+            operands
+                .into_iter()
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+        ));
+    }
+
+    let opcode: Opcode = mnemonic
+        .parse()
+        .map_err(|_| AssembleErrorKind::UnknownMnemonic(mnemonic.to_string()))?;
+    let mut modes = [ParameterMode::default(); 3];
+    let mut args = Vec::new();
+    for (i, operand) in operands.iter().enumerate() {
+        let (mode, value) = parse_operand(operand)?;
+        if let Some(slot) = modes.get_mut(i) {
+            *slot = mode;
+        }
+        args.push(value);
+    }
+
+    let instruction = Instruction::new(opcode, modes);
+    if args.len() != instruction.arg_len() {
+        return Err(AssembleErrorKind::OperandCount(
+            opcode,
+            instruction.arg_len(),
+            args.len(),
+        ));
+    }
+    Ok(Statement::Instruction(instruction, args))
+}
+
+/// Assembles Intcode source into [`Memory`].
+///
+/// Each line holds an optional address (checked against where the line actually lands), any
+/// number of `label:` definitions and then either an instruction or a `.data` directive.
+/// Operands are written `[12]` for position mode, `#5` for immediate mode and `rb+3` for
+/// relative mode, and labels may stand in for any position or immediate value. Everything
+/// after a `;` is a comment. This accepts the output of [`Disassembler`] as-is.
+///
+/// [`Disassembler`]: crate::disassembler::Disassembler
+pub fn assemble(source: &str) -> Result<Memory, AssembleError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_no = i + 1;
+        let error = |kind| AssembleError {
+            line: line_no,
+            kind,
+        };
+
+        let mut rest = line.split(';').next().unwrap().trim();
+
+        // Listings from the disassembler lead with the address of each line
+        let first = rest.split_whitespace().next().unwrap_or_default();
+        if !first.is_empty() && first.chars().all(|c| c.is_ascii_digit()) {
+            let listed = first
+                .parse()
+                .map_err(|_| error(AssembleErrorKind::InvalidOperand(first.to_string())))?;
+            if listed != address {
+                return Err(error(AssembleErrorKind::AddressMismatch(listed, address)));
+            }
+            rest = rest[first.len()..].trim_start();
+        }
+
+        while let Some((label, tail)) = rest.split_once(':') {
+            let label = label.trim();
+            if !is_identifier(label) {
+                return Err(error(AssembleErrorKind::InvalidLabel(label.to_string())));
+            }
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(error(AssembleErrorKind::DuplicateLabel(label.to_string())));
+            }
+            rest = tail.trim();
+        }
+
+        if !rest.is_empty() {
+            let statement = parse_statement(rest).map_err(error)?;
+            address += statement.len();
+            statements.push((line_no, statement));
+        }
+    }
+
+    let mut words = Vec::with_capacity(address);
+    for (line, statement) in statements {
+        let resolve = |value: Value| match value {
+            Value::Literal(value) => Ok(value),
+            Value::Label(label) => labels
+                .get(&label)
+                .map(|&address| Int::try_from(address).unwrap())
+                .ok_or(AssembleError {
+                    line,
+                    kind: AssembleErrorKind::UndefinedLabel(label),
+                }),
+        };
+        match statement {
+            Statement::Instruction(instruction, args) => {
+                words.push(instruction.into());
+                for arg in args {
+                    words.push(resolve(arg)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in values {
+                    words.push(resolve(value)?);
+                }
+            }
+        }
+    }
+
+    Ok(Memory::from(words))
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::disassembler::Disassembler;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; Outputs 1 if the input is 8, otherwise 0
+                in    [value]
+                eq    [value], #8, [value]
+                out   [value]
+                hlt
+            value: .data -1
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            Memory::try_from("3,9,1008,9,8,9,4,9,99,-1").unwrap()
+        );
+
+        let source = "
+            loop: add rb+1, rb-2, rb
+                  jt #1, #loop
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            Memory::try_from("22201,1,-2,0,1105,1,0").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        for (source, line, kind) in [
+            (
+                "hlt\nmov [1], [2]",
+                2,
+                AssembleErrorKind::UnknownMnemonic("mov".to_string()),
+            ),
+            (
+                "out [1], [2]",
+                1,
+                AssembleErrorKind::OperandCount(Opcode::Out, 1, 2),
+            ),
+            (
+                "\n\nout 1",
+                3,
+                AssembleErrorKind::InvalidOperand("1".to_string()),
+            ),
+            (
+                "a: hlt\na: hlt",
+                2,
+                AssembleErrorKind::DuplicateLabel("a".to_string()),
+            ),
+            (
+                "hlt\n\njt #1, #nowhere",
+                3,
+                AssembleErrorKind::UndefinedLabel("nowhere".to_string()),
+            ),
+            (
+                "0000 hlt\n0002 hlt",
+                2,
+                AssembleErrorKind::AddressMismatch(2, 1),
+            ),
+        ] {
+            assert_eq!(assemble(source), Err(AssembleError { line, kind }));
+        }
+    }
+
+    fn round_trip(memory: &Memory) {
+        let disassembler = Disassembler::new(memory);
+        for listing in [
+            disassembler.disassemble(),
+            disassembler.with_follow_jumps().disassemble(),
+        ] {
+            assert_eq!(&assemble(&listing.to_string()).unwrap(), memory);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_samples() {
+        for program in [
+            "3,9,8,9,10,9,4,9,99,-1,8",
+            "3,3,1107,-1,8,3,4,3,99",
+            "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+            "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0",
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        ] {
+            round_trip(&Memory::try_from(program).unwrap());
+        }
+    }
+
+    fn word() -> impl Strategy<Value = i64> {
+        prop_oneof![
+            // Mostly plausible instructions and small addresses, with the odd wild value
+            (
+                0i64..3,
+                0i64..3,
+                0i64..3,
+                prop::sample::select(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 99])
+            )
+                .prop_map(|(a, b, c, op)| ((a * 10 + b) * 10 + c) * 100 + op),
+            -5i64..40,
+            any::<i64>(),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(words in prop::collection::vec(word(), 0..64)) {
+            let memory = Memory::from(words.into_iter().map(Int::new).collect::<Vec<_>>());
+            round_trip(&memory);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use aoc_2019_day_07::disassembler::Disassembler;
+use aoc_2019_day_07::intterpreter::{IntTerpreter, Memory, Status};
+use clap::{Parser, Subcommand};
+
+/// Runs and inspects Intcode programs.
+#[derive(Parser)]
+#[clap(name = "intcode")]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Runs a program, reading input from stdin unless it's given up front
+    Run {
+        program: PathBuf,
+        /// Comma separated values to use as input instead of stdin
+        #[clap(long, value_delimiter = ',', allow_hyphen_values = true)]
+        input: Option<Vec<i64>>,
+        /// Reads input and prints output as ASCII text
+        #[clap(long)]
+        ascii: bool,
+        /// Prints memory once the program stops
+        #[clap(long)]
+        dump: bool,
+        /// Prints every instruction to stderr as it's executed
+        #[clap(long)]
+        trace: bool,
+        /// Gives up after executing this many instructions
+        #[clap(long)]
+        max_steps: Option<usize>,
+        /// Prints the N most executed instructions to stderr once the program stops
+        #[clap(long, value_name = "N")]
+        profile: Option<usize>,
+    },
+    /// Prints a program as assembly
+    Disasm {
+        program: PathBuf,
+        /// Only lists code reachable from the start of the program
+        #[clap(long)]
+        follow_jumps: bool,
+    },
+}
+
+fn load(path: &Path) -> Result<Memory, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    Memory::try_from(source.as_str())
+        .map_err(|_| format!("{} isn't an Intcode program", path.display()))
+}
+
+fn run(
+    memory: Memory,
+    input: Option<Vec<i64>>,
+    ascii: bool,
+    dump: bool,
+    trace: bool,
+    max_steps: Option<usize>,
+    profile: Option<usize>,
+) -> Result<(), String> {
+    let mut interpreter = IntTerpreter::new().with_memory(memory.clone());
+    if let Some(input) = input {
+        interpreter = interpreter.with_input(&input);
+    } else if ascii {
+        interpreter = interpreter.with_ascii_stdin();
+    }
+    if ascii {
+        interpreter = interpreter.with_ascii_stdout();
+    }
+    if trace {
+        interpreter = interpreter.with_trace();
+    }
+    if profile.is_some() {
+        interpreter = interpreter.with_profile();
+    }
+
+    let mut steps = 0;
+    while interpreter.status().is_ready() {
+        if max_steps == Some(steps) {
+            break;
+        }
+        interpreter.step();
+        steps += 1;
+        for entry in interpreter.take_trace().unwrap_or_default() {
+            eprintln!("{}", entry);
+        }
+    }
+
+    if let (Some(n), Some(stats)) = (profile, interpreter.profile()) {
+        eprint!("{}", stats.report(&memory, n));
+    }
+    if dump {
+        let memory = interpreter.memory().unwrap();
+        let words: Vec<String> = (0..memory.len()).map(|i| memory[i].to_string()).collect();
+        println!("{}", words.join(","));
+    }
+
+    match interpreter.status() {
+        Status::Finished => Ok(()),
+        Status::Ready => Err(format!("gave up after {} steps", steps)),
+        Status::Overflow(address) => Err(format!("overflow at {:04}", address)),
+        Status::BadAddress(address) => Err(format!("bad address at {:04}", address)),
+        Status::Error | Status::Stopped => Err(format!(
+            "stopped at {:04} after {} steps",
+            interpreter.ip(),
+            steps
+        )),
+    }
+}
+
+fn main() {
+    let result = match Args::parse().command {
+        Command::Run {
+            program,
+            input,
+            ascii,
+            dump,
+            trace,
+            max_steps,
+            profile,
+        } => load(&program)
+            .and_then(|memory| run(memory, input, ascii, dump, trace, max_steps, profile)),
+        Command::Disasm {
+            program,
+            follow_jumps,
+        } => load(&program).map(|memory| {
+            let disassembler = Disassembler::new(&memory);
+            let disassembler = if follow_jumps {
+                disassembler.with_follow_jumps()
+            } else {
+                disassembler
+            };
+            print!("{}", disassembler.disassemble());
+        }),
+    };
+
+    if let Err(message) = result {
+        eprintln!("intcode: {}", message);
+        exit(1);
+    }
+}
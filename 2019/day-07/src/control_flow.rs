@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use crate::disassembler::{Disassembler, Line};
+use crate::intterpreter::{Memory, Opcode, ParameterMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// Execution carries on to the next instruction, including when a branch isn't taken.
+    Fallthrough,
+    /// A `jt`/`jf` instruction jumps.
+    Taken,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub target: usize,
+    pub kind: EdgeKind,
+}
+
+/// A run of instructions that always execute one after another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub lines: Vec<Line>,
+    pub edges: Vec<Edge>,
+}
+
+impl BasicBlock {
+    pub fn start(&self) -> usize {
+        self.lines[0].address()
+    }
+
+    /// Returns the address just past the end of the block.
+    pub fn end(&self) -> usize {
+        match self.lines.last() {
+            Some(Line::Code { address, args, .. }) => address + args.len() + 1,
+            Some(Line::Data { address, .. }) => address + 1,
+            None => unreachable!(),
+        }
+    }
+}
+
+/// An instruction that writes to a word of reachable code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CodeWrite {
+    pub address: usize,
+    pub target: usize,
+}
+
+/// The control flow of a program, found by decoding everything reachable from address 0.
+///
+/// Only immediate jump targets can be followed, so jumps through memory are reported rather
+/// than explored. Likewise, writes are only checked against code when their address is known
+/// up front, so relative mode writes aren't reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    blocks: BTreeMap<usize, BasicBlock>,
+    indirect_jumps: Vec<usize>,
+    code_writes: Vec<CodeWrite>,
+    unreachable: Vec<Range<usize>>,
+}
+
+impl ControlFlowGraph {
+    pub fn new(memory: &Memory) -> Self {
+        let listing = Disassembler::new(memory).with_follow_jumps().disassemble();
+        let code: Vec<&Line> = listing
+            .lines()
+            .iter()
+            .filter(|line| matches!(line, Line::Code { .. }))
+            .collect();
+        let starts: BTreeSet<usize> = code.iter().map(|line| line.address()).collect();
+
+        // Blocks start at the entry point, at jump targets and after branches
+        let mut leaders: BTreeSet<usize> =
+            code.iter().filter_map(|line| line.taken_target()).collect();
+        leaders.insert(0);
+        for line in &code {
+            if line.branch_target().is_some() || is_indirect_jump(line) {
+                leaders.extend(line.fallthrough());
+            }
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut current: Vec<Line> = Vec::new();
+        for &line in &code {
+            let continues = current
+                .last()
+                .and_then(Line::fallthrough)
+                .is_some_and(|next| next == line.address());
+            if !current.is_empty() && (!continues || leaders.contains(&line.address())) {
+                let block = finish_block(std::mem::take(&mut current), &starts);
+                blocks.insert(block.start(), block);
+            }
+            current.push(line.clone());
+        }
+        if !current.is_empty() {
+            let block = finish_block(current, &starts);
+            blocks.insert(block.start(), block);
+        }
+
+        let indirect_jumps = code
+            .iter()
+            .filter(|line| is_indirect_jump(line))
+            .map(|line| line.address())
+            .collect();
+
+        let mut covered = vec![false; memory.len()];
+        for line in &code {
+            if let Line::Code { address, args, .. } = line {
+                covered[*address..=address + args.len()]
+                    .iter_mut()
+                    .for_each(|c| *c = true);
+            }
+        }
+
+        let code_writes = code
+            .iter()
+            .filter_map(|line| match line {
+                Line::Code {
+                    address,
+                    instruction,
+                    args,
+                } => {
+                    let i = instruction.output_arg()?;
+                    let target = usize::try_from(args[i]).ok()?;
+                    (instruction.modes()[i] == ParameterMode::Position
+                        && covered.get(target) == Some(&true))
+                    .then_some(CodeWrite {
+                        address: *address,
+                        target,
+                    })
+                }
+                Line::Data { .. } => None,
+            })
+            .collect();
+
+        let mut unreachable: Vec<Range<usize>> = Vec::new();
+        for (address, _) in covered.iter().enumerate().filter(|(_, &c)| !c) {
+            match unreachable.last_mut() {
+                Some(range) if range.end == address => range.end += 1,
+                _ => unreachable.push(address..address + 1),
+            }
+        }
+
+        Self {
+            blocks,
+            indirect_jumps,
+            code_writes,
+            unreachable,
+        }
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &BasicBlock> {
+        self.blocks.values()
+    }
+
+    /// Returns the block starting at `address`.
+    pub fn block(&self, address: usize) -> Option<&BasicBlock> {
+        self.blocks.get(&address)
+    }
+
+    /// Returns the addresses of `jt`/`jf` instructions that jump to an address read from
+    /// memory.
+    pub fn indirect_jumps(&self) -> &[usize] {
+        &self.indirect_jumps
+    }
+
+    /// Returns the writes that land on reachable code, which make the program self-modifying.
+    pub fn code_writes(&self) -> &[CodeWrite] {
+        &self.code_writes
+    }
+
+    /// Returns the ranges of memory that are never executed. These are usually data.
+    pub fn unreachable(&self) -> &[Range<usize>] {
+        &self.unreachable
+    }
+
+    /// Renders the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cfg {\n    node [shape=box fontname=monospace];\n");
+        for block in self.blocks() {
+            let label: String = block
+                .lines
+                .iter()
+                .map(|line| format!("{}\\l", line).replace('"', "\\\""))
+                .collect();
+            dot += &format!("    b{} [label=\"{}\"];\n", block.start(), label);
+        }
+        for block in self.blocks() {
+            for edge in &block.edges {
+                dot += &format!("    b{} -> b{}", block.start(), edge.target);
+                if edge.kind == EdgeKind::Taken {
+                    dot += " [label=\"taken\"]";
+                }
+                dot += ";\n";
+            }
+        }
+        dot + "}\n"
+    }
+}
+
+fn is_indirect_jump(line: &Line) -> bool {
+    match line {
+        Line::Code { instruction, .. } => {
+            matches!(instruction.opcode(), Opcode::Bot | Opcode::Bof)
+                && instruction.modes()[1] != ParameterMode::Immediate
+        }
+        Line::Data { .. } => false,
+    }
+}
+
+fn finish_block(lines: Vec<Line>, starts: &BTreeSet<usize>) -> BasicBlock {
+    let last = lines.last().unwrap();
+    let mut edges = Vec::new();
+    if let Some(target) = last.taken_target().filter(|target| starts.contains(target)) {
+        edges.push(Edge {
+            target,
+            kind: EdgeKind::Taken,
+        });
+    }
+    if let Some(target) = last.fallthrough().filter(|target| starts.contains(target)) {
+        edges.push(Edge {
+            target,
+            kind: EdgeKind::Fallthrough,
+        });
+    }
+    BasicBlock { lines, edges }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    fn edges(cfg: &ControlFlowGraph) -> Vec<(usize, usize, EdgeKind)> {
+        cfg.blocks()
+            .flat_map(|block| {
+                block
+                    .edges
+                    .iter()
+                    .map(move |edge| (block.start(), edge.target, edge.kind))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_blocks() {
+        let memory = Memory::try_from(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        )
+        .unwrap();
+        let cfg = ControlFlowGraph::new(&memory);
+        assert_eq!(
+            cfg.blocks()
+                .map(|block| (block.start(), block.end()))
+                .collect::<Vec<_>>(),
+            [(0, 6), (6, 25), (25, 26)]
+        );
+        assert_eq!(
+            edges(&cfg),
+            [
+                (0, 6, EdgeKind::Fallthrough),
+                (6, 6, EdgeKind::Taken),
+                (6, 25, EdgeKind::Fallthrough)
+            ]
+        );
+        assert_eq!(cfg.unreachable(), [Range { start: 26, end: 29 }]);
+        assert!(cfg.indirect_jumps().is_empty());
+        assert!(cfg.code_writes().is_empty());
+
+        assert_eq!(
+            cfg.to_dot(),
+            "digraph cfg {\n    \
+                 node [shape=box fontname=monospace];\n    \
+                 b0 [label=\"0000  in    [26]\\l0002  add   [26], #-4, [26]\\l\"];\n    \
+                 b6 [label=\"0006  in    [27]\\l0008  mul   [27], #2, [27]\\l\
+                     0012  add   [27], [26], [27]\\l0016  out   [27]\\l\
+                     0018  add   [28], #-1, [28]\\l0022  jt    [28], #6\\l\"];\n    \
+                 b25 [label=\"0025  hlt\\l\"];\n    \
+                 b0 -> b6;\n    \
+                 b6 -> b6 [label=\"taken\"];\n    \
+                 b6 -> b25;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_findings() {
+        let memory = assemble(
+            "
+                  in  [v]
+                  jt  [v], [v]
+                  add #104, #0, [out]
+            out:  out [v]
+                  hlt
+            v:    .data 0
+            ",
+        )
+        .unwrap();
+        let cfg = ControlFlowGraph::new(&memory);
+        assert_eq!(cfg.indirect_jumps(), [2]);
+        assert_eq!(
+            cfg.code_writes(),
+            [CodeWrite {
+                address: 5,
+                target: 9
+            }]
+        );
+        assert_eq!(cfg.unreachable(), [Range { start: 12, end: 13 }]);
+        assert_eq!(edges(&cfg), [(0, 5, EdgeKind::Fallthrough)]);
+        assert_eq!(
+            cfg.blocks()
+                .map(|block| (block.start(), block.end()))
+                .collect::<Vec<_>>(),
+            [(0, 5), (5, 12)]
+        );
+    }
+}
@@ -0,0 +1,426 @@
+use std::collections::BTreeSet;
+use std::io::{stdin, stdout, Write};
+
+use crate::disassembler::{Disassembler, Line};
+use crate::intterpreter::{Int, IntTerpreter, Status};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(usize),
+    Watchpoint { address: usize, old: Int, new: Int },
+    NeedsInput,
+    Finished,
+    Error,
+    Stopped,
+    Overflow(usize),
+    BadAddress(usize),
+}
+
+impl std::fmt::Display for Stop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stop::Breakpoint(address) => write!(f, "breakpoint at {:04}", address),
+            Stop::Watchpoint { address, old, new } => {
+                write!(f, "watchpoint at {:04}: {} -> {}", address, old, new)
+            }
+            Stop::NeedsInput => write!(f, "waiting for input"),
+            Stop::Finished => write!(f, "finished"),
+            Stop::Error => write!(f, "error"),
+            Stop::Stopped => write!(f, "stopped"),
+            Stop::Overflow(address) => write!(f, "overflow at {:04}", address),
+            Stop::BadAddress(address) => write!(f, "bad address at {:04}", address),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Debugger {
+    interpreter: IntTerpreter,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(interpreter: IntTerpreter) -> Self {
+        Self {
+            interpreter,
+            ..Default::default()
+        }
+    }
+
+    pub fn interpreter(&self) -> &IntTerpreter {
+        &self.interpreter
+    }
+
+    pub fn into_inner(self) -> IntTerpreter {
+        self.interpreter
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<usize> {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.insert(address)
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn watchpoints(&self) -> &BTreeSet<usize> {
+        &self.watchpoints
+    }
+
+    pub fn add_watchpoint(&mut self, address: usize) -> bool {
+        self.watchpoints.insert(address)
+    }
+
+    pub fn remove_watchpoint(&mut self, address: usize) -> bool {
+        self.watchpoints.remove(&address)
+    }
+
+    /// Returns the instruction about to be executed.
+    pub fn current(&self) -> Option<Line> {
+        let memory = self.interpreter.memory()?;
+        (self.interpreter.ip() < memory.len())
+            .then(|| Disassembler::new(memory).line_at(self.interpreter.ip()))
+    }
+
+    pub fn read(&self, address: usize) -> Option<Int> {
+        self.interpreter.read_memory(address).ok()
+    }
+
+    /// Returns `false` if the address is outside of memory.
+    pub fn patch(&mut self, address: usize, value: Int) -> bool {
+        address
+            .try_into()
+            .is_ok_and(|address| self.interpreter.write_memory(value, address).is_ok())
+    }
+
+    pub fn input(&self) -> Option<&Vec<Int>> {
+        self.interpreter.intput()
+    }
+
+    pub fn input_mut(&mut self) -> Option<&mut Vec<Int>> {
+        self.interpreter.input_mut()
+    }
+
+    pub fn output(&self) -> Option<&Vec<Int>> {
+        self.interpreter.output()
+    }
+
+    pub fn output_mut(&mut self) -> Option<&mut Vec<Int>> {
+        self.interpreter.output_mut()
+    }
+
+    /// Returns the address the current instruction is going to write to, if any.
+    fn write_target(&self, line: &Line) -> Option<usize> {
+        if let Line::Code {
+            instruction, args, ..
+        } = line
+        {
+            self.interpreter
+                .eval_address(instruction, args, instruction.output_arg()?)
+                .ok()?
+                .try_into()
+                .ok()
+        } else {
+            None
+        }
+    }
+
+    /// Executes a single instruction, returning why execution should stop, if anything.
+    pub fn step(&mut self) -> Option<Stop> {
+        // Reading from an empty buffer is an error, so stop before that happens and give the
+        // chance to provide more input
+        if self.interpreter.needs_input() {
+            return Some(Stop::NeedsInput);
+        }
+
+        let watched = self
+            .current()
+            .as_ref()
+            .and_then(|line| self.write_target(line))
+            .filter(|address| self.watchpoints.contains(address))
+            .and_then(|address| Some((address, self.read(address)?)));
+
+        match self.interpreter.step() {
+            Status::Finished => Some(Stop::Finished),
+            Status::Error => Some(Stop::Error),
+            Status::Stopped => Some(Stop::Stopped),
+            Status::Overflow(address) => Some(Stop::Overflow(address)),
+            Status::BadAddress(address) => Some(Stop::BadAddress(address)),
+            Status::Ready => {
+                if let Some((address, old)) = watched {
+                    let new = self.read(address).unwrap();
+                    Some(Stop::Watchpoint { address, old, new })
+                } else if self.breakpoints.contains(&self.interpreter.ip()) {
+                    Some(Stop::Breakpoint(self.interpreter.ip()))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Executes instructions until a breakpoint or watchpoint is hit or the program stops.
+    pub fn resume(&mut self) -> Stop {
+        loop {
+            if let Some(stop) = self.step() {
+                return stop;
+            }
+        }
+    }
+
+    /// Runs a single debugger command, returning the text to show for it.
+    ///
+    /// | Command               | Effect                                           |
+    /// |-----------------------|--------------------------------------------------|
+    /// | `step`, `s`           | Execute one instruction                          |
+    /// | `continue`, `c`       | Execute until something stops the program        |
+    /// | `break N`, `b N`      | Set a breakpoint at address `N`                  |
+    /// | `delete N`, `d N`     | Remove the breakpoint at address `N`             |
+    /// | `watch N`, `w N`      | Stop whenever address `N` is written             |
+    /// | `unwatch N`           | Remove the watchpoint on address `N`             |
+    /// | `x N [COUNT]`         | Show `COUNT` words of memory starting at `N`     |
+    /// | `set N VALUE`         | Write `VALUE` to address `N`                     |
+    /// | `input [V,...]`       | Show the input buffer, or append to it           |
+    /// | `output`              | Show the output buffer                           |
+    /// | `clear input\|output` | Empty the input or output buffer                 |
+    pub fn command(&mut self, line: &str) -> String {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        let address = |i: usize| args.get(i).and_then(|s| s.parse::<usize>().ok());
+
+        match (command, &args[..]) {
+            ("step" | "s", []) => {
+                let current = self.current().map(|line| line.to_string());
+                let stop = self.step();
+                match (current, stop) {
+                    (Some(current), Some(stop)) => format!("{}\n{}", current, stop),
+                    (Some(current), None) => current,
+                    (None, stop) => stop.unwrap_or(Stop::Finished).to_string(),
+                }
+            }
+            ("continue" | "c", []) => self.resume().to_string(),
+            ("break" | "b", [_]) => match address(0) {
+                Some(address) if self.add_breakpoint(address) => {
+                    format!("breakpoint set at {:04}", address)
+                }
+                Some(_) => "breakpoint already set".to_string(),
+                None => "invalid address".to_string(),
+            },
+            ("delete" | "d", [_]) => match address(0) {
+                Some(address) if self.remove_breakpoint(address) => {
+                    format!("breakpoint at {:04} removed", address)
+                }
+                _ => "no such breakpoint".to_string(),
+            },
+            ("watch" | "w", [_]) => match address(0) {
+                Some(address) if self.add_watchpoint(address) => {
+                    format!("watching {:04}", address)
+                }
+                Some(_) => "watchpoint already set".to_string(),
+                None => "invalid address".to_string(),
+            },
+            ("unwatch", [_]) => match address(0) {
+                Some(address) if self.remove_watchpoint(address) => {
+                    format!("no longer watching {:04}", address)
+                }
+                _ => "no such watchpoint".to_string(),
+            },
+            ("x", [_] | [_, _]) => {
+                match (address(0), args.get(1).map_or(Some(1), |_| address(1))) {
+                    (Some(start), Some(count)) => (start..start.saturating_add(count))
+                        .map_while(|address| {
+                            Some(format!("{:04}  {}", address, self.read(address)?))
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    _ => "invalid address".to_string(),
+                }
+            }
+            ("set", [_, value]) => match (address(0), value.parse()) {
+                (Some(address), Ok(value)) if self.patch(address, value) => {
+                    format!("{:04}  {}", address, value)
+                }
+                _ => "invalid address or value".to_string(),
+            },
+            ("input", []) => match self.input() {
+                Some(input) => format!("{:?}", input.iter().map(i64::from).collect::<Vec<_>>()),
+                None => "input is not buffered".to_string(),
+            },
+            ("input", [values]) => {
+                match values
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<Result<Vec<Int>, _>>()
+                {
+                    Ok(values) => match self.input_mut() {
+                        Some(input) => {
+                            input.extend(values);
+                            format!("{} values queued", input.len())
+                        }
+                        None => "input is not buffered".to_string(),
+                    },
+                    Err(_) => "invalid input".to_string(),
+                }
+            }
+            ("output", []) => match self.output() {
+                Some(output) => format!("{:?}", output.iter().map(i64::from).collect::<Vec<_>>()),
+                None => "output is not buffered".to_string(),
+            },
+            ("clear", ["input"]) => match self.input_mut() {
+                Some(input) => {
+                    input.clear();
+                    "input cleared".to_string()
+                }
+                None => "input is not buffered".to_string(),
+            },
+            ("clear", ["output"]) => match self.output_mut() {
+                Some(output) => {
+                    output.clear();
+                    "output cleared".to_string()
+                }
+                None => "output is not buffered".to_string(),
+            },
+            _ => format!("unknown command `{}`", line.trim()),
+        }
+    }
+
+    /// Reads commands from stdin until it closes or the program stops running.
+    pub fn repl(&mut self) {
+        let mut buf = String::new();
+        loop {
+            if let Some(current) = self.current() {
+                println!("{}", current);
+            }
+            print!("> ");
+            stdout().flush().unwrap();
+
+            buf.clear();
+            if stdin().read_line(&mut buf).unwrap() == 0 {
+                break;
+            }
+            let response = self.command(&buf);
+            if !response.is_empty() {
+                println!("{}", response);
+            }
+            if !self.interpreter.status().is_ready() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intterpreter::Memory;
+
+    fn debugger(program: &str, input: &[i64]) -> Debugger {
+        Debugger::new(
+            IntTerpreter::new()
+                .with_memory(Memory::try_from(program).unwrap())
+                .with_input(input)
+                .with_buffer(),
+        )
+    }
+
+    #[test]
+    fn test_step() {
+        // Day 5: outputs whether the input is equal to 8
+        let mut debugger = debugger("3,9,8,9,10,9,4,9,99,-1,8", &[8]);
+        assert_eq!(debugger.current().unwrap().to_string(), "0000  in    [9]");
+        assert_eq!(debugger.step(), None);
+        assert_eq!(debugger.read(9), Some(Int::new(8)));
+        assert_eq!(
+            debugger.current().unwrap().to_string(),
+            "0002  eq    [9], [10], [9]"
+        );
+        assert_eq!(debugger.command("s"), "0002  eq    [9], [10], [9]");
+        assert_eq!(debugger.command("x 9 3"), "0009  1\n0010  8");
+        assert_eq!(debugger.resume(), Stop::Finished);
+        assert_eq!(debugger.output().unwrap(), &vec![Int::new(1)]);
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut debugger = debugger("3,9,8,9,10,9,4,9,99,-1,8", &[7]);
+        assert_eq!(debugger.command("w 9"), "watching 0009");
+        assert_eq!(
+            debugger.resume(),
+            Stop::Watchpoint {
+                address: 9,
+                old: Int::new(-1),
+                new: Int::new(7)
+            }
+        );
+
+        // Patching the cell doesn't trigger the watchpoint, but changes the result
+        assert_eq!(debugger.command("set 10 7"), "0010  7");
+        assert_eq!(
+            debugger.resume(),
+            Stop::Watchpoint {
+                address: 9,
+                old: Int::new(7),
+                new: Int::new(1)
+            }
+        );
+        assert_eq!(debugger.command("c"), "finished");
+        assert_eq!(debugger.command("output"), "[1]");
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        // Day 5: compares the input against 8, jumping around to pick the output
+        let program = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+        for (input, breakpoint) in [(7, 31), (8, 22), (9, 36)] {
+            let mut debugger = debugger(program, &[input]);
+            for address in [22, 31, 36] {
+                debugger.add_breakpoint(address);
+            }
+            assert_eq!(debugger.resume(), Stop::Breakpoint(breakpoint));
+            debugger.remove_breakpoint(breakpoint);
+            assert_eq!(debugger.resume(), Stop::Finished);
+        }
+    }
+
+    #[test]
+    fn test_buffers() {
+        // Day 7: a single amplifier of the feedback loop
+        let program =
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+        let mut debugger = debugger(program, &[9]);
+        debugger.add_breakpoint(18);
+
+        assert_eq!(debugger.resume(), Stop::NeedsInput);
+        assert_eq!(debugger.command("input"), "[]");
+        assert_eq!(debugger.command("input 0"), "1 values queued");
+        assert_eq!(debugger.resume(), Stop::Breakpoint(18));
+        assert_eq!(debugger.command("output"), "[5]");
+
+        assert_eq!(debugger.command("clear output"), "output cleared");
+        assert_eq!(debugger.command("input 5,7"), "2 values queued");
+        assert_eq!(debugger.resume(), Stop::Breakpoint(18));
+        assert_eq!(debugger.output().unwrap(), &vec![Int::new(15)]);
+        assert_eq!(debugger.input().unwrap(), &vec![Int::new(7)]);
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut debugger = debugger("3,9,8,9,10,9,4,9,99,-1,8", &[8]);
+        assert_eq!(
+            debugger.command("set 18446744073709551615 1"),
+            "invalid address or value"
+        );
+        assert_eq!(debugger.command("set 11 1"), "invalid address or value");
+        assert_eq!(
+            debugger.command("x 9 18446744073709551615"),
+            "0009  -1\n0010  8"
+        );
+        assert_eq!(debugger.command("x 18446744073709551615 2"), "");
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::intterpreter::{encode_ascii, Int};
+
+/// Something attached to an interpreter that supplies its input or takes its output.
+pub trait Device<W = Int>: std::fmt::Debug + Send {
+    /// Returns the next input, or `None` if the device has nothing left to give.
+    fn input(&mut self) -> Option<W>;
+
+    /// Returns `false` if the device can't take the value.
+    fn output(&mut self, value: W) -> bool;
+}
+
+/// A device shared between an interpreter and whoever wants to look at it afterwards.
+pub type SharedDevice<W = Int> = Arc<Mutex<dyn Device<W>>>;
+
+/// Collects output in `(x, y, value)` triples, drawing each value at its position.
+/// It never has any input, so give the interpreter another device to read from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Screen {
+    pixels: BTreeMap<(i64, i64), Int>,
+    pending: Vec<Int>,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> Option<Int> {
+        self.pixels.get(&(x, y)).copied()
+    }
+
+    pub fn pixels(&self) -> &BTreeMap<(i64, i64), Int> {
+        &self.pixels
+    }
+
+    /// Draws everything between the top left and bottom right pixels, one row per line.
+    pub fn render(&self, glyph: impl Fn(Option<Int>) -> char) -> String {
+        render(self.pixels.keys().copied(), |x, y| glyph(self.get(x, y)))
+    }
+}
+
+impl Device for Screen {
+    fn input(&mut self) -> Option<Int> {
+        None
+    }
+
+    fn output(&mut self, value: Int) -> bool {
+        self.pending.push(value);
+        if let [x, y, value] = self.pending[..] {
+            self.pixels.insert((x.into(), y.into()), value);
+            self.pending.clear();
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub fn turn_left(self) -> Self {
+        match self {
+            Self::Up => Self::Left,
+            Self::Right => Self::Up,
+            Self::Down => Self::Right,
+            Self::Left => Self::Down,
+        }
+    }
+
+    pub fn turn_right(self) -> Self {
+        match self {
+            Self::Up => Self::Right,
+            Self::Right => Self::Down,
+            Self::Down => Self::Left,
+            Self::Left => Self::Up,
+        }
+    }
+
+    /// Returns the change in position from one step this way. `y` grows downwards.
+    pub fn offset(self) -> (i64, i64) {
+        match self {
+            Self::Up => (0, -1),
+            Self::Right => (1, 0),
+            Self::Down => (0, 1),
+            Self::Left => (-1, 0),
+        }
+    }
+}
+
+/// A robot on a grid of panels. It reports the colour of the panel it's on, then takes pairs of
+/// outputs: a colour to paint the panel, and which way to turn (`0` for left, `1` for right)
+/// before moving forward one panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turtle {
+    position: (i64, i64),
+    direction: Direction,
+    panels: BTreeMap<(i64, i64), Int>,
+    paint: Option<Int>,
+}
+
+impl Default for Turtle {
+    fn default() -> Self {
+        Self {
+            position: (0, 0),
+            direction: Direction::Up,
+            panels: BTreeMap::new(),
+            paint: None,
+        }
+    }
+}
+
+impl Turtle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Paints the starting panel before the robot does anything.
+    pub fn with_start_colour(mut self, colour: Int) -> Self {
+        self.panels.insert(self.position, colour);
+        self
+    }
+
+    pub fn position(&self) -> (i64, i64) {
+        self.position
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the colour of a panel. Panels start out as `0`.
+    pub fn colour(&self, x: i64, y: i64) -> Int {
+        self.panels.get(&(x, y)).copied().unwrap_or_default()
+    }
+
+    /// Returns every panel that's been painted at least once.
+    pub fn panels(&self) -> &BTreeMap<(i64, i64), Int> {
+        &self.panels
+    }
+
+    /// Draws the painted area with `#` for panels of colour `1` and `.` for everything else.
+    pub fn render(&self) -> String {
+        render(self.panels.keys().copied(), |x, y| {
+            if self.colour(x, y) == 1 {
+                '#'
+            } else {
+                '.'
+            }
+        })
+    }
+}
+
+impl Device for Turtle {
+    fn input(&mut self) -> Option<Int> {
+        Some(self.colour(self.position.0, self.position.1))
+    }
+
+    fn output(&mut self, value: Int) -> bool {
+        match self.paint.take() {
+            None => self.paint = Some(value),
+            Some(colour) => {
+                self.panels.insert(self.position, colour);
+                self.direction = match i64::from(value) {
+                    0 => self.direction.turn_left(),
+                    1 => self.direction.turn_right(),
+                    _ => return false,
+                };
+                let (dx, dy) = self.direction.offset();
+                self.position = (self.position.0 + dx, self.position.1 + dy);
+            }
+        }
+        true
+    }
+}
+
+/// Feeds the program a fixed list of inputs and keeps whatever it outputs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    inputs: VecDeque<Int>,
+    outputs: Vec<Int>,
+}
+
+impl Script {
+    pub fn new<T>(inputs: &[T]) -> Self
+    where
+        Int: From<T>,
+        T: Copy,
+    {
+        Self {
+            inputs: inputs.iter().map(|&t| Int::from(t)).collect(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Feeds each line in as character codes, followed by a newline.
+    pub fn from_lines(lines: &[&str]) -> Self {
+        Self {
+            inputs: lines.iter().flat_map(|line| encode_ascii(line)).collect(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Returns the inputs that haven't been read yet.
+    pub fn remaining(&self) -> &VecDeque<Int> {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[Int] {
+        &self.outputs
+    }
+}
+
+impl Device for Script {
+    fn input(&mut self) -> Option<Int> {
+        self.inputs.pop_front()
+    }
+
+    fn output(&mut self, value: Int) -> bool {
+        self.outputs.push(value);
+        true
+    }
+}
+
+fn render(positions: impl Iterator<Item = (i64, i64)>, glyph: impl Fn(i64, i64) -> char) -> String {
+    let positions: Vec<_> = positions.collect();
+    let (Some(min_x), Some(max_x)) = (
+        positions.iter().map(|p| p.0).min(),
+        positions.iter().map(|p| p.0).max(),
+    ) else {
+        return String::new();
+    };
+    let min_y = positions.iter().map(|p| p.1).min().unwrap();
+    let max_y = positions.iter().map(|p| p.1).max().unwrap();
+    (min_y..=max_y)
+        .map(|y| (min_x..=max_x).map(|x| glyph(x, y)).collect::<String>() + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::intterpreter::{IntTerpreter, Status};
+
+    fn run(source: &str, device: SharedDevice) -> Status {
+        IntTerpreter::new()
+            .with_memory(assemble(source).unwrap())
+            .with_device(device)
+            .execute()
+    }
+
+    #[test]
+    fn test_screen() {
+        let screen = Arc::new(Mutex::new(Screen::new()));
+        let source = "
+            out #0
+            out #0
+            out #1
+            out #2
+            out #1
+            out #2
+            out #2
+            out #1
+            out #1
+            out #1
+            out #0
+            out #2
+            hlt
+        ";
+        assert_eq!(run(source, screen.clone()), Status::Finished);
+
+        let screen = screen.lock().unwrap();
+        assert_eq!(screen.get(2, 1), Some(Int::new(1)));
+        assert_eq!(screen.get(0, 1), None);
+        let glyph = |pixel: Option<Int>| match pixel.map(i64::from) {
+            Some(1) => '#',
+            Some(2) => 'o',
+            _ => ' ',
+        };
+        assert_eq!(screen.render(glyph), "#o \n  #\n");
+    }
+
+    #[test]
+    fn test_separate_devices() {
+        // Draws whatever pixels the script gives it
+        let source = "
+            loop: in  [v]
+                  out [v]
+                  jt  #1, #loop
+            v:    .data 0
+        ";
+        let script = Arc::new(Mutex::new(Script::new(&[3, 1, 2, 0, 0, 1])));
+        let screen = Arc::new(Mutex::new(Screen::new()));
+        let status = IntTerpreter::new()
+            .with_memory(assemble(source).unwrap())
+            .with_input_device(script.clone())
+            .with_output_device(screen.clone())
+            .execute();
+        assert_eq!(status, Status::Stopped);
+
+        let screen = screen.lock().unwrap();
+        assert_eq!(screen.get(3, 1), Some(Int::new(2)));
+        assert_eq!(screen.get(0, 0), Some(Int::new(1)));
+        assert!(script.lock().unwrap().outputs().is_empty());
+    }
+
+    #[test]
+    fn test_turtle() {
+        // Paints and turns as given by its input script, reading the panel colour each time
+        let moves = [(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)];
+        let source: String = moves
+            .iter()
+            .map(|(paint, turn)| format!("in [c]\nout #{}\nout #{}\n", paint, turn))
+            .chain(["hlt\nc: .data 0\n".to_string()])
+            .collect();
+
+        let turtle = Arc::new(Mutex::new(Turtle::new()));
+        assert_eq!(run(&source, turtle.clone()), Status::Finished);
+
+        let turtle = turtle.lock().unwrap();
+        assert_eq!(turtle.panels().len(), 6);
+        assert_eq!(turtle.position(), (0, -1));
+        assert_eq!(turtle.direction(), Direction::Left);
+        assert_eq!(turtle.render(), "..#\n..#\n##.\n");
+    }
+
+    #[test]
+    fn test_script() {
+        // Doubles everything until it's given a zero
+        let source = "
+            loop: in  [v]
+                  jf  [v], #end
+                  mul [v], #2, [v]
+                  out [v]
+                  jt  #1, #loop
+            end:  hlt
+            v:    .data 0
+        ";
+        let script = Arc::new(Mutex::new(Script::new(&[1, 5, -3, 0, 9])));
+        assert_eq!(run(source, script.clone()), Status::Finished);
+        let script = script.lock().unwrap();
+        assert_eq!(script.outputs(), [Int::new(2), Int::new(10), Int::new(-6)]);
+        assert_eq!(script.remaining(), &[Int::new(9)]);
+
+        // Running out of input stops the program rather than failing it
+        let script = Arc::new(Mutex::new(Script::from_lines(&["a"])));
+        assert_eq!(run(source, script.clone()), Status::Stopped);
+        assert_eq!(
+            script.lock().unwrap().outputs(),
+            [Int::new(194), Int::new(20)]
+        );
+    }
+}
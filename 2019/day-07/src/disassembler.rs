@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::intterpreter::{Instruction, Int, Memory, Opcode, ParameterMode};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Code {
+        address: usize,
+        instruction: Instruction,
+        args: Vec<Int>,
+    },
+    Data {
+        address: usize,
+        value: Int,
+    },
+}
+
+impl Line {
+    fn write<'a>(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        label: impl Fn(usize) -> Option<&'a str>,
+    ) -> std::fmt::Result {
+        match self {
+            Line::Code {
+                address,
+                instruction,
+                args,
+            } => {
+                let target = self.branch_target();
+                let operands = instruction
+                    .modes()
+                    .iter()
+                    .zip(args)
+                    .enumerate()
+                    .map(|(i, (&mode, &arg))| match mode {
+                        ParameterMode::Position => format!("[{}]", arg),
+                        ParameterMode::Immediate => {
+                            match target.filter(|_| i == 1).and_then(&label) {
+                                Some(label) => format!("#{}", label),
+                                None => format!("#{}", arg),
+                            }
+                        }
+                        ParameterMode::Relative if arg < Int::new(0) => format!("rb{}", arg),
+                        ParameterMode::Relative => format!("rb+{}", arg),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let text = format!("{:04}  {:<5} {}", address, instruction.opcode(), operands);
+                write!(f, "{}", text.trim_end())
+            }
+            Line::Data { address, value } => write!(f, "{:04}  .data {}", address, value),
+        }
+    }
+
+    pub fn address(&self) -> usize {
+        match self {
+            Line::Code { address, .. } | Line::Data { address, .. } => *address,
+        }
+    }
+
+    /// Returns the immediate jump target of a `jt`/`jf` instruction, if it has one.
+    pub fn branch_target(&self) -> Option<usize> {
+        match self {
+            Line::Code {
+                instruction, args, ..
+            } if matches!(instruction.opcode(), Opcode::Bot | Opcode::Bof)
+                && instruction.modes()[1] == ParameterMode::Immediate =>
+            {
+                args[1].try_into().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether a `jt`/`jf` instruction with an immediate condition always jumps.
+    fn always_jumps(&self) -> Option<bool> {
+        match self {
+            Line::Code {
+                instruction, args, ..
+            } if matches!(instruction.opcode(), Opcode::Bot | Opcode::Bof)
+                && instruction.modes()[0] == ParameterMode::Immediate =>
+            {
+                Some(bool::from(args[0]) == (instruction.opcode() == Opcode::Bot))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the address of the next line if execution can carry on to it.
+    pub fn fallthrough(&self) -> Option<usize> {
+        match self {
+            Line::Code {
+                address,
+                instruction,
+                args,
+            } if instruction.opcode() != Opcode::Hlt && self.always_jumps() != Some(true) => {
+                Some(address + args.len() + 1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the immediate jump target of a `jt`/`jf` instruction, unless its condition
+    /// means it can never jump.
+    pub fn taken_target(&self) -> Option<usize> {
+        self.branch_target()
+            .filter(|_| self.always_jumps() != Some(false))
+    }
+}
+
+impl std::fmt::Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, |_| None)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    lines: Vec<Line>,
+    labels: BTreeMap<usize, String>,
+}
+
+impl Listing {
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    pub fn label(&self, address: usize) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+}
+
+impl std::fmt::Display for Listing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            if let Some(label) = self.label(line.address()) {
+                writeln!(f, "{}:", label)?;
+            }
+            line.write(f, |address| self.label(address))?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Disassembler<'a> {
+    memory: &'a Memory,
+    follow_jumps: bool,
+}
+
+impl<'a> Disassembler<'a> {
+    pub fn new(memory: &'a Memory) -> Self {
+        Self {
+            memory,
+            follow_jumps: false,
+        }
+    }
+
+    /// Only treat words reachable from address 0 as code, following immediate jump targets.
+    /// Everything else is listed as `.data`.
+    pub fn with_follow_jumps(mut self) -> Self {
+        self.follow_jumps = true;
+        self
+    }
+
+    pub fn disassemble(&self) -> Listing {
+        let lines = if self.follow_jumps {
+            self.trace_reachable()
+        } else {
+            self.linear_sweep()
+        };
+
+        let starts: BTreeSet<usize> = lines.iter().map(Line::address).collect();
+        let labels = lines
+            .iter()
+            .filter_map(Line::branch_target)
+            .filter(|target| starts.contains(target))
+            .map(|target| (target, format!("L{:04}", target)))
+            .collect();
+
+        Listing { lines, labels }
+    }
+
+    /// Decodes the instruction at `address` if it and all of its arguments fit in memory.
+    fn decode(&self, address: usize) -> Option<(Instruction, Vec<Int>)> {
+        let word = self.memory.get(address)?;
+        let instruction = Instruction::try_from(word).ok()?;
+        // Non-canonical encodings can't be written back, so treat those words as data
+        if Int::from(instruction) != word {
+            return None;
+        }
+        let args = (address + 1..address + 1 + instruction.arg_len())
+            .map(|i| self.memory.get(i))
+            .collect::<Option<Vec<Int>>>()?;
+        Some((instruction, args))
+    }
+
+    /// Returns the line starting at `address`, decoded as an instruction if possible.
+    pub fn line_at(&self, address: usize) -> Line {
+        match self.decode(address) {
+            Some((instruction, args)) => Line::Code {
+                address,
+                instruction,
+                args,
+            },
+            None => self.data(address),
+        }
+    }
+
+    fn data(&self, address: usize) -> Line {
+        Line::Data {
+            address,
+            value: self.memory[address],
+        }
+    }
+
+    fn linear_sweep(&self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut address = 0;
+        while address < self.memory.len() {
+            let line = self.line_at(address);
+            address += match line {
+                Line::Code { ref args, .. } => args.len() + 1,
+                Line::Data { .. } => 1,
+            };
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn trace_reachable(&self) -> Vec<Line> {
+        let mut claimed = vec![false; self.memory.len()];
+        let mut code = BTreeMap::new();
+        let mut pending = vec![0];
+
+        while let Some(address) = pending.pop() {
+            if claimed.get(address) != Some(&false) {
+                continue;
+            }
+            let (instruction, args) = match self.decode(address) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+
+            // Don't let instructions overlap ones we've already decoded
+            let span = address..address + args.len() + 1;
+            if claimed[span.clone()].iter().any(|&c| c) {
+                continue;
+            }
+            claimed[span.clone()].iter_mut().for_each(|c| *c = true);
+
+            let line = Line::Code {
+                address,
+                instruction,
+                args,
+            };
+            pending.extend(line.fallthrough());
+            pending.extend(line.taken_target());
+            code.insert(address, line);
+        }
+
+        let mut lines = Vec::new();
+        let mut address = 0;
+        while address < self.memory.len() {
+            match code.remove(&address) {
+                Some(line) => {
+                    if let Line::Code { ref args, .. } = line {
+                        address += args.len();
+                    }
+                    lines.push(line);
+                }
+                None => lines.push(self.data(address)),
+            }
+            address += 1;
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disassemble(program: &str, follow_jumps: bool) -> String {
+        let memory = Memory::try_from(program).unwrap();
+        let disassembler = Disassembler::new(&memory);
+        if follow_jumps {
+            disassembler.with_follow_jumps().disassemble().to_string()
+        } else {
+            disassembler.disassemble().to_string()
+        }
+    }
+
+    #[test]
+    fn test_linear() {
+        assert_eq!(
+            disassemble("3,9,8,9,10,9,4,9,99,-1,8", false),
+            "0000  in    [9]\n\
+             0002  eq    [9], [10], [9]\n\
+             0006  out   [9]\n\
+             0008  hlt\n\
+             0009  .data -1\n\
+             0010  .data 8\n"
+        );
+        assert_eq!(
+            disassemble("1101,5,-3,0,109,4,204,-2,22201,1,2,3,99", false),
+            "0000  add   #5, #-3, [0]\n\
+             0004  arb   #4\n\
+             0006  out   rb-2\n\
+             0008  add   rb+1, rb+2, rb+3\n\
+             0012  hlt\n"
+        );
+    }
+
+    #[test]
+    fn test_follow_jumps() {
+        let program =
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+        assert_eq!(
+            disassemble(program, true),
+            "0000  in    [26]\n\
+             0002  add   [26], #-4, [26]\n\
+             L0006:\n\
+             0006  in    [27]\n\
+             0008  mul   [27], #2, [27]\n\
+             0012  add   [27], [26], [27]\n\
+             0016  out   [27]\n\
+             0018  add   [28], #-1, [28]\n\
+             0022  jt    [28], #L0006\n\
+             0025  hlt\n\
+             0026  .data 0\n\
+             0027  .data 0\n\
+             0028  .data 5\n"
+        );
+
+        // The words skipped by an unconditional jump decode fine, but are never executed
+        assert_eq!(
+            disassemble("1106,0,7,1,0,0,0,99", false),
+            "0000  jf    #0, #L0007\n\
+             0003  add   [0], [0], [0]\n\
+             L0007:\n\
+             0007  hlt\n"
+        );
+        assert_eq!(
+            disassemble("1106,0,7,1,0,0,0,99", true),
+            "0000  jf    #0, #L0007\n\
+             0003  .data 1\n\
+             0004  .data 0\n\
+             0005  .data 0\n\
+             0006  .data 0\n\
+             L0007:\n\
+             0007  hlt\n"
+        );
+    }
+}
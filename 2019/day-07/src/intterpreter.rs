@@ -1,4 +1,20 @@
-use std::io::stdin;
+use std::hash::Hash;
+use std::io::{stdin, stdout, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::device::SharedDevice;
+use crate::profile::Profile;
+
+use num_bigint::BigInt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 macro_rules! impl_from {
     ( $for_type:ty, $( $type:ty ),* ) => {
@@ -52,7 +68,9 @@ macro_rules! impl_into {
     };
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
 pub struct Int(i64);
 
 impl Int {
@@ -115,35 +133,154 @@ impl std::ops::Mul for Int {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum InputMode {
+/// A value that can be stored in [`Memory`].
+pub trait Word:
+    Clone
+    + Default
+    + std::fmt::Debug
+    + std::fmt::Display
+    + FromStr
+    + PartialEq
+    + Eq
+    + PartialOrd
+    + Ord
+    + Hash
+    + Send
+    + Sync
+    + Serialize
+    + DeserializeOwned
+    + 'static
+{
+    fn from_i64(value: i64) -> Self;
+
+    /// Returns `None` if the value doesn't fit in an `i64`.
+    fn to_i64(&self) -> Option<i64>;
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+
+    fn to_address(&self) -> Option<usize> {
+        self.to_i64().and_then(|value| usize::try_from(value).ok())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.to_i64() == Some(0)
+    }
+}
+
+impl Word for Int {
+    fn from_i64(value: i64) -> Self {
+        Int(value)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        Some(self.0)
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Int)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        self.0.checked_mul(rhs.0).map(Int)
+    }
+}
+
+/// Words that never overflow, for programs that outgrow an `i64`.
+impl Word for BigInt {
+    fn from_i64(value: i64) -> Self {
+        BigInt::from(value)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self).ok()
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InputMode<W = Int> {
     Stdin,
-    Buffer(Vec<Int>),
+    Buffer(Vec<W>),
+    /// Reads lines from stdin and feeds them in one character at a time.
+    AsciiStdin(Vec<W>),
+    /// Blocks until a value is received, giving up after the timeout if there is one.
+    Channel(Receiver<W>, Option<Duration>),
+    Device(SharedDevice<W>),
 }
 
-impl Default for InputMode {
+impl<W> Default for InputMode<W> {
     fn default() -> Self {
         Self::Stdin
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum OutputMode {
+#[derive(Debug, Clone)]
+pub enum OutputMode<W = Int> {
     Stdout,
-    Buffer(Vec<Int>),
+    Buffer(Vec<W>),
+    /// Prints ASCII characters as text, and anything else as a number on its own line.
+    AsciiStdout,
+    Channel(Sender<W>),
+    Device(SharedDevice<W>),
 }
 
-impl Default for OutputMode {
+impl<W> Default for OutputMode<W> {
     fn default() -> Self {
         Self::Stdout
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AsciiOutput<W = Int> {
+    Text(String),
+    Raw(W),
+}
+
+/// Returns the character a value stands for, if it's in the ASCII range.
+pub fn ascii_char<W: Word>(value: &W) -> Option<char> {
+    value
+        .to_i64()
+        .and_then(|value| u8::try_from(value).ok())
+        .filter(u8::is_ascii)
+        .map(char::from)
+}
+
+/// Encodes a line of text as character codes, followed by a newline.
+pub fn encode_ascii<W: Word>(line: &str) -> Vec<W> {
+    line.chars()
+        .chain(std::iter::once('\n'))
+        .map(|c| W::from_i64(c as i64))
+        .collect()
+}
+
+/// Decodes output into runs of text, keeping values outside of the ASCII range as they are.
+pub fn decode_ascii<W: Word>(values: &[W]) -> Vec<AsciiOutput<W>> {
+    let mut decoded = Vec::new();
+    for value in values {
+        match (ascii_char(value), decoded.last_mut()) {
+            (Some(c), Some(AsciiOutput::Text(text))) => text.push(c),
+            (Some(c), _) => decoded.push(AsciiOutput::Text(c.to_string())),
+            (None, _) => decoded.push(AsciiOutput::Raw(value.clone())),
+        }
+    }
+    decoded
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum ParameterMode {
     Position,
     Immediate,
+    Relative,
 }
 
 impl Default for ParameterMode {
@@ -159,12 +296,23 @@ impl TryFrom<Int> for ParameterMode {
         Ok(match value.0 {
             0 => Self::Position,
             1 => Self::Immediate,
+            2 => Self::Relative,
             _ => return Err(()),
         })
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+impl From<ParameterMode> for Int {
+    fn from(mode: ParameterMode) -> Self {
+        Int(match mode {
+            ParameterMode::Position => 0,
+            ParameterMode::Immediate => 1,
+            ParameterMode::Relative => 2,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum Opcode {
     Add, // Add
@@ -175,6 +323,7 @@ pub enum Opcode {
     Bof, // Branch on false
     Clt, // Check less than
     Ceq, // Check equal
+    Arb, // Adjust relative base
     Hlt, // Halt
 }
 
@@ -191,19 +340,101 @@ impl TryFrom<Int> for Opcode {
             6 => Self::Bof,
             7 => Self::Clt,
             8 => Self::Ceq,
+            9 => Self::Arb,
             99 => Self::Hlt,
             _ => return Err(()),
         })
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+impl From<Opcode> for Int {
+    fn from(opcode: Opcode) -> Self {
+        Int(match opcode {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::Str => 3,
+            Opcode::Out => 4,
+            Opcode::Bot => 5,
+            Opcode::Bof => 6,
+            Opcode::Clt => 7,
+            Opcode::Ceq => 8,
+            Opcode::Arb => 9,
+            Opcode::Hlt => 99,
+        })
+    }
+}
+
+impl Opcode {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Mul => "mul",
+            Self::Str => "in",
+            Self::Out => "out",
+            Self::Bot => "jt",
+            Self::Bof => "jf",
+            Self::Clt => "lt",
+            Self::Ceq => "eq",
+            Self::Arb => "arb",
+            Self::Hlt => "hlt",
+        }
+    }
+}
+
+impl std::str::FromStr for Opcode {
+    type Err = (); // TODO: Custom errors
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "add" => Self::Add,
+            "mul" => Self::Mul,
+            "in" => Self::Str,
+            "out" => Self::Out,
+            "jt" => Self::Bot,
+            "jf" => Self::Bof,
+            "lt" => Self::Clt,
+            "eq" => Self::Ceq,
+            "arb" => Self::Arb,
+            "hlt" => Self::Hlt,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl std::fmt::Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.mnemonic().fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Instruction {
     opcode: Opcode,
     modes: [ParameterMode; 3],
 }
 
 impl Instruction {
+    pub fn new(opcode: Opcode, modes: [ParameterMode; 3]) -> Self {
+        Self { opcode, modes }
+    }
+
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    pub fn modes(&self) -> &[ParameterMode] {
+        &self.modes[..self.arg_len()]
+    }
+
+    /// Returns the index of the parameter this instruction writes to, if any.
+    pub fn output_arg(&self) -> Option<usize> {
+        match self.opcode {
+            Opcode::Add | Opcode::Mul | Opcode::Clt | Opcode::Ceq => Some(2),
+            Opcode::Str => Some(0),
+            Opcode::Out | Opcode::Bot | Opcode::Bof | Opcode::Arb | Opcode::Hlt => None,
+        }
+    }
+
     pub fn arg_len(&self) -> usize {
         match self.opcode {
             Opcode::Add => 3,
@@ -215,6 +446,7 @@ impl Instruction {
             Opcode::Bof => 2,
             Opcode::Clt => 3,
             Opcode::Ceq => 3,
+            Opcode::Arb => 1,
         }
     }
 }
@@ -223,55 +455,104 @@ impl TryFrom<Int> for Instruction {
     type Error = (); // TODO: Custom errors
 
     fn try_from(value: Int) -> Result<Self, Self::Error> {
-        let value = format!("{:05}", value);
-        let (modes, opcode) = value.split_at(3);
-        let modes: Vec<ParameterMode> = modes
-            .chars()
-            .map(|c| ParameterMode::try_from(Int(c.to_digit(10).unwrap() as i64)).unwrap())
+        // Instructions are at most five non-negative digits
+        if !(0..100_000).contains(&value.0) {
+            return Err(());
+        }
+        let opcode = Opcode::try_from(Int(value.0 % 100))?;
+        let mut modes = [ParameterMode::default(); 3];
+        let mut digits = value.0 / 100;
+        for mode in modes.iter_mut() {
+            *mode = ParameterMode::try_from(Int(digits % 10))?;
+            digits /= 10;
+        }
+        Ok(Self { opcode, modes })
+    }
+}
+
+impl From<Instruction> for Int {
+    fn from(instruction: Instruction) -> Self {
+        // Modes of unused parameters are dropped, giving the canonical encoding
+        instruction
+            .modes()
+            .iter()
             .rev()
-            .collect();
-        let opcode = Opcode::try_from(opcode.parse::<Int>().unwrap())?;
-        Ok(Self {
-            opcode,
-            modes: modes[..3].try_into().unwrap(),
-        })
+            .fold(Int(0), |acc, &mode| Int(acc.0 * 10) + mode.into())
+            * Int(100)
+            + instruction.opcode.into()
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Memory {
-    words: Vec<Int>,
+/// Decodes a word as an instruction. Words that don't fit in an `i64` can't be one.
+fn decode<W: Word>(word: &W) -> Result<Instruction, ()> {
+    Instruction::try_from(Int(word.to_i64().ok_or(())?))
+}
+
+/// Number of words in each [`Memory`] page.
+const PAGE_SIZE: usize = 256;
+
+/// Program memory. Clones share their pages, and only copy the ones they write to.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(from = "Vec<W>", into = "Vec<W>", bound = "W: Word")]
+pub struct Memory<W: Word = Int> {
+    pages: Vec<Arc<Vec<W>>>,
+    len: usize,
 }
 
-impl Memory {
+impl<W: Word> Memory<W> {
     pub fn len(&self) -> usize {
-        self.words.len()
+        self.len
     }
 
     pub fn is_empty(&self) -> bool {
-        self.words.is_empty()
+        self.len == 0
     }
 
-    pub fn get(&self, index: usize) -> Option<Int> {
-        (index < self.len()).then(|| self.words[index])
+    pub fn get(&self, index: usize) -> Option<W> {
+        self.pages
+            .get(index / PAGE_SIZE)?
+            .get(index % PAGE_SIZE)
+            .cloned()
     }
 }
 
-impl std::ops::Index<usize> for Memory {
-    type Output = Int;
+impl<W: Word> std::ops::Index<usize> for Memory<W> {
+    type Output = W;
 
     fn index(&self, index: usize) -> &Self::Output {
-        self.words.index(index)
+        &self.pages[index / PAGE_SIZE][index % PAGE_SIZE]
     }
 }
 
-impl std::ops::IndexMut<usize> for Memory {
+impl<W: Word> std::ops::IndexMut<usize> for Memory<W> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        self.words.index_mut(index)
+        &mut Arc::make_mut(&mut self.pages[index / PAGE_SIZE])[index % PAGE_SIZE]
     }
 }
 
-impl TryFrom<&str> for Memory {
+impl<W: Word> From<Vec<W>> for Memory<W> {
+    fn from(words: Vec<W>) -> Self {
+        Self {
+            len: words.len(),
+            pages: words
+                .chunks(PAGE_SIZE)
+                .map(|page| Arc::new(page.to_vec()))
+                .collect(),
+        }
+    }
+}
+
+impl<W: Word> From<Memory<W>> for Vec<W> {
+    fn from(memory: Memory<W>) -> Self {
+        let mut words = Vec::with_capacity(memory.len);
+        for page in memory.pages {
+            words.extend(Arc::try_unwrap(page).unwrap_or_else(|page| (*page).clone()));
+        }
+        words
+    }
+}
+
+impl<W: Word> TryFrom<&str> for Memory<W> {
     type Error = (); // TODO: Custom errors
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
@@ -285,20 +566,45 @@ impl TryFrom<&str> for Memory {
                     None
                 }
             })
-            .collect::<Result<Vec<Int>, _>>()
+            .collect::<Result<Vec<W>, _>>()
         {
-            Ok(Memory { words })
+            Ok(Memory::from(words))
         } else {
             Err(())
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Why an instruction couldn't get to one of its addresses.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AddressError {
+    #[error("relative address overflowed")]
+    Overflow,
+    #[error("address is outside of memory")]
+    OutOfRange,
+}
+
+impl AddressError {
+    /// Returns the status of a machine whose instruction at `ip` failed like this.
+    fn status(self, ip: usize) -> Status {
+        match self {
+            AddressError::Overflow => Status::Overflow(ip),
+            AddressError::OutOfRange => Status::BadAddress(ip),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum Status {
     Ready,
     Finished,
     Error,
+    /// An input or output channel was closed or timed out.
+    Stopped,
+    /// The instruction at this address overflowed.
+    Overflow(usize),
+    /// The instruction at this address used an address outside of memory.
+    BadAddress(usize),
 }
 
 impl Status {
@@ -322,6 +628,27 @@ impl Status {
     pub fn is_error(&self) -> bool {
         matches!(self, Self::Error)
     }
+
+    /// Returns `true` if the status is [`Stopped`].
+    ///
+    /// [`Stopped`]: Status::Stopped
+    pub fn is_stopped(&self) -> bool {
+        matches!(self, Self::Stopped)
+    }
+
+    /// Returns `true` if the status is [`Overflow`].
+    ///
+    /// [`Overflow`]: Status::Overflow
+    pub fn is_overflow(&self) -> bool {
+        matches!(self, Self::Overflow(_))
+    }
+
+    /// Returns `true` if the status is [`BadAddress`].
+    ///
+    /// [`BadAddress`]: Status::BadAddress
+    pub fn is_bad_address(&self) -> bool {
+        matches!(self, Self::BadAddress(_))
+    }
 }
 
 impl Default for Status {
@@ -330,35 +657,136 @@ impl Default for Status {
     }
 }
 
-#[derive(Debug, Default)]
-pub struct IntTerpreter {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MemoryWrite<W = Int> {
+    pub address: usize,
+    pub old: W,
+    pub new: W,
+}
+
+/// Everything a single executed instruction did.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TraceEntry<W = Int> {
+    pub ip: usize,
+    pub instruction: Instruction,
+    /// Parameter values after applying their modes. Parameters that are written to hold the
+    /// address they point at instead.
+    pub operands: Vec<W>,
+    pub writes: Vec<MemoryWrite<W>>,
+    pub input: Option<W>,
+    pub output: Option<W>,
+}
+
+/// The state of an interpreter at some point in its execution.
+///
+/// Input and output are only captured when they're buffered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "W: Word")]
+pub struct Snapshot<W: Word = Int> {
+    pub ip: usize,
+    pub relative_base: W,
+    pub memory: Option<Memory<W>>,
+    pub status: Status,
+    pub input: Option<Vec<W>>,
+    pub output: Option<Vec<W>>,
+}
+
+impl<W: Word> Snapshot<W> {
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Clones get their own copy of everything except channel and device I/O, which stays shared. Two
+/// clones reading from one channel race for each value, so give a fork its own input with
+/// [`IntTerpreter::with_channel_input`] before running it.
+#[derive(Debug, Default, Clone)]
+pub struct IntTerpreter<W: Word = Int> {
     ip: usize,
-    memory: Option<Memory>,
+    relative_base: W,
+    memory: Option<Memory<W>>,
     status: Status,
-    input: InputMode,
-    output: OutputMode,
+    input: InputMode<W>,
+    output: OutputMode<W>,
+    trace: Option<Vec<TraceEntry<W>>>,
+    decoded: Option<Vec<Option<Instruction>>>,
+    profile: Option<Profile>,
 }
 
-impl IntTerpreter {
+impl<W: Word> IntTerpreter<W> {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn with_memory(mut self, memory: Memory) -> Self {
+    pub fn with_memory(mut self, memory: Memory<W>) -> Self {
         self.memory = Some(memory);
+        self.clear_decoded();
         self
     }
 
     pub fn with_input<T>(mut self, input: &[T]) -> Self
     where
-        Int: From<T>,
+        W: From<T>,
         T: Copy,
     {
-        let vecc = input.iter().map(|t| Int::from(*t)).collect();
+        let vecc = input.iter().map(|t| W::from(*t)).collect();
         self.input = InputMode::Buffer(vecc);
         self
     }
 
+    /// Feeds each line in as character codes, followed by a newline.
+    pub fn with_ascii_input(mut self, lines: &[&str]) -> Self {
+        self.input = InputMode::Buffer(lines.iter().flat_map(|line| encode_ascii(line)).collect());
+        self
+    }
+
+    pub fn with_ascii_stdin(mut self) -> Self {
+        self.input = InputMode::AsciiStdin(Vec::new());
+        self
+    }
+
+    pub fn with_ascii_stdout(mut self) -> Self {
+        self.output = OutputMode::AsciiStdout;
+        self
+    }
+
+    /// Plays the program from the terminal, reading commands line by line and printing its
+    /// output as text.
+    pub fn with_ascii_terminal(self) -> Self {
+        self.with_ascii_stdin().with_ascii_stdout()
+    }
+
+    /// Reads input from a channel. Without a timeout this blocks until the channel is closed.
+    pub fn with_channel_input(mut self, receiver: Receiver<W>, timeout: Option<Duration>) -> Self {
+        self.input = InputMode::Channel(receiver, timeout);
+        self
+    }
+
+    pub fn with_channel_output(mut self, sender: Sender<W>) -> Self {
+        self.output = OutputMode::Channel(sender);
+        self
+    }
+
+    /// Reads input from and writes output to the same device.
+    pub fn with_device(self, device: SharedDevice<W>) -> Self {
+        self.with_input_device(device.clone())
+            .with_output_device(device)
+    }
+
+    pub fn with_input_device(mut self, device: SharedDevice<W>) -> Self {
+        self.input = InputMode::Device(device);
+        self
+    }
+
+    pub fn with_output_device(mut self, device: SharedDevice<W>) -> Self {
+        self.output = OutputMode::Device(device);
+        self
+    }
+
     pub fn with_buffer(mut self) -> Self {
         self.output = OutputMode::Buffer(Vec::new());
         self
@@ -369,13 +797,42 @@ impl IntTerpreter {
         self
     }
 
-    pub fn set_memory(&mut self, memory: Memory) {
+    /// Records a [`TraceEntry`] for every instruction executed.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Vec::new());
+        self
+    }
+
+    /// Records a [`Profile`] of everything executed.
+    pub fn with_profile(mut self) -> Self {
+        self.profile = Some(Profile::default());
+        self
+    }
+
+    /// Keeps every instruction after decoding it the first time, until something writes over it.
+    pub fn with_decode_cache(mut self) -> Self {
+        self.decoded = Some(Vec::new());
+        self.clear_decoded();
+        self
+    }
+
+    fn clear_decoded(&mut self) {
+        let len = self.memory.as_ref().map_or(0, Memory::len);
+        if let Some(decoded) = self.decoded.as_mut() {
+            decoded.clear();
+            decoded.resize(len, None);
+        }
+    }
+
+    pub fn set_memory(&mut self, memory: Memory<W>) {
         self.memory = Some(memory);
+        self.clear_decoded();
         self.reset();
     }
 
     pub fn reset(&mut self) {
         self.ip = 0;
+        self.relative_base = W::default();
         self.status = Status::Ready;
         if let Some(output) = self.output_mut() {
             output.clear();
@@ -383,69 +840,179 @@ impl IntTerpreter {
         if let Some(input) = self.input_mut() {
             input.clear();
         }
+        if let Some(trace) = self.trace.as_mut() {
+            trace.clear();
+        }
+        if let Some(profile) = self.profile.as_mut() {
+            *profile = Profile::default();
+        }
     }
 
-    pub fn eval_arg(&self, instruction: &Instruction, args: &[Int], argi: usize) -> Int {
-        let arg = args[argi];
-        match instruction.modes[argi] {
-            ParameterMode::Position => self.memory.as_ref().unwrap()[arg.0 as usize],
-            ParameterMode::Immediate => arg,
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            ip: self.ip,
+            relative_base: self.relative_base.clone(),
+            memory: self.memory.clone(),
+            status: self.status,
+            input: self.intput().cloned(),
+            output: self.output().cloned(),
         }
     }
 
-    pub fn read_memory(&self, address: usize) -> Result<Int, ()> {
-        if let Some(ref memory) = self.memory {
-            if address < memory.len() {
-                return Ok(memory[address]);
+    /// Puts the interpreter back into the state captured by a snapshot. Buffers in the snapshot
+    /// replace whatever input or output was in use, and the trace is left alone.
+    pub fn restore(&mut self, snapshot: Snapshot<W>) {
+        self.ip = snapshot.ip;
+        self.relative_base = snapshot.relative_base;
+        self.memory = snapshot.memory;
+        self.clear_decoded();
+        self.status = snapshot.status;
+        if let Some(input) = snapshot.input {
+            self.input = InputMode::Buffer(input);
+        }
+        if let Some(output) = snapshot.output {
+            self.output = OutputMode::Buffer(output);
+        }
+    }
+
+    pub fn eval_arg(
+        &self,
+        instruction: &Instruction,
+        args: &[W],
+        argi: usize,
+    ) -> Result<W, AddressError> {
+        match instruction.modes[argi] {
+            ParameterMode::Immediate => Ok(args[argi].clone()),
+            ParameterMode::Position | ParameterMode::Relative => {
+                let address = self.eval_address(instruction, args, argi)?;
+                address
+                    .to_address()
+                    .and_then(|address| self.read_memory(address).ok())
+                    .ok_or(AddressError::OutOfRange)
             }
         }
-        Err(())
     }
 
-    pub fn write_memory(&mut self, value: Int, address: Int) {
-        if let Some(ref mut memory) = self.memory {
-            memory[address.0 as usize] = value;
+    /// Fails if a relative address overflows.
+    pub fn eval_address(
+        &self,
+        instruction: &Instruction,
+        args: &[W],
+        argi: usize,
+    ) -> Result<W, AddressError> {
+        let arg = &args[argi];
+        match instruction.modes[argi] {
+            ParameterMode::Relative => self
+                .relative_base
+                .checked_add(arg)
+                .ok_or(AddressError::Overflow),
+            _ => Ok(arg.clone()),
+        }
+    }
+
+    pub fn read_memory(&self, address: usize) -> Result<W, ()> {
+        self.memory
+            .as_ref()
+            .and_then(|memory| memory.get(address))
+            .ok_or(())
+    }
+
+    /// Fails if the address is outside of memory.
+    pub fn write_memory(&mut self, value: W, address: W) -> Result<(), AddressError> {
+        let address = address.to_address().ok_or(AddressError::OutOfRange)?;
+        match self.memory {
+            Some(ref mut memory) if address < memory.len() => memory[address] = value,
+            _ => return Err(AddressError::OutOfRange),
+        }
+        if let Some(slot) = self
+            .decoded
+            .as_mut()
+            .and_then(|decoded| decoded.get_mut(address))
+        {
+            *slot = None;
         }
+        Ok(())
     }
 
     pub fn next_instruction(&mut self) -> Result<Instruction, ()> {
-        let instruction = Instruction::try_from(self.read_memory(self.ip)?)?;
+        let cached = self
+            .decoded
+            .as_ref()
+            .and_then(|decoded| decoded.get(self.ip).copied().flatten());
+        let instruction = match cached {
+            Some(instruction) => instruction,
+            None => {
+                let instruction = decode(&self.read_memory(self.ip)?)?;
+                if let Some(slot) = self
+                    .decoded
+                    .as_mut()
+                    .and_then(|decoded| decoded.get_mut(self.ip))
+                {
+                    *slot = Some(instruction);
+                }
+                instruction
+            }
+        };
         self.ip += 1;
         Ok(instruction)
     }
 
-    pub fn consume_args(&mut self, instruction: &Instruction) -> Result<Vec<Int>, ()> {
-        let args = (self.ip..self.ip + instruction.arg_len())
-            .filter_map(|i| self.read_memory(i).ok())
-            .collect::<Vec<Int>>();
-
-        if args.len() == instruction.arg_len() {
-            self.ip += instruction.arg_len();
-            Ok(args)
-        } else {
-            Err(())
+    /// Reads the arguments of an instruction. Slots past [`Instruction::arg_len`] are left as
+    /// zero.
+    pub fn consume_args(&mut self, instruction: &Instruction) -> Result<[W; 3], ()> {
+        let mut args: [W; 3] = Default::default();
+        for (i, arg) in args.iter_mut().take(instruction.arg_len()).enumerate() {
+            *arg = self.read_memory(self.ip + i)?;
         }
+        self.ip += instruction.arg_len();
+        Ok(args)
     }
 
-    pub fn take_input(&mut self) -> Option<Int> {
+    pub fn take_input(&mut self) -> Option<W> {
         match self.input {
             InputMode::Stdin => {
                 let mut buf = String::new();
                 stdin().read_line(&mut buf).unwrap();
-                buf.parse().ok()
+                buf.trim().parse().ok()
             }
             InputMode::Buffer(ref mut input) => (!input.is_empty()).then(|| input.remove(0)),
+            InputMode::AsciiStdin(ref mut pending) => {
+                if pending.is_empty() {
+                    stdout().flush().unwrap();
+                    let mut buf = String::new();
+                    if stdin().read_line(&mut buf).unwrap() == 0 {
+                        return None;
+                    }
+                    *pending = encode_ascii(buf.trim_end_matches(['\r', '\n']));
+                }
+                Some(pending.remove(0))
+            }
+            InputMode::Channel(ref receiver, timeout) => match timeout {
+                Some(timeout) => receiver.recv_timeout(timeout).ok(),
+                None => receiver.recv().ok(),
+            },
+            InputMode::Device(ref device) => device.lock().unwrap().input(),
         }
     }
 
-    pub fn intput(&self) -> Option<&Vec<Int>> {
+    /// Queues a line of text on the input buffer. Returns `false` if input isn't buffered.
+    pub fn push_line(&mut self, line: &str) -> bool {
+        if let Some(input) = self.input_mut() {
+            input.extend(encode_ascii(line));
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn intput(&self) -> Option<&Vec<W>> {
         match self.input {
             InputMode::Buffer(ref buf) => Some(buf),
             _ => None,
         }
     }
 
-    pub fn input_mut(&mut self) -> Option<&mut Vec<Int>> {
+    pub fn input_mut(&mut self) -> Option<&mut Vec<W>> {
         match self.input {
             InputMode::Buffer(ref mut buf) => Some(buf),
             _ => None,
@@ -456,96 +1023,146 @@ impl IntTerpreter {
         self.status
     }
 
-    pub fn output(&self) -> Option<&Vec<Int>> {
+    /// Returns `true` if the next instruction reads input but the input buffer is empty.
+    pub fn needs_input(&self) -> bool {
+        self.status.is_ready()
+            && self
+                .read_memory(self.ip)
+                .ok()
+                .and_then(|word| decode(&word).ok())
+                .is_some_and(|instruction| instruction.opcode == Opcode::Str)
+            && self.intput().is_some_and(Vec::is_empty)
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn memory(&self) -> Option<&Memory<W>> {
+        self.memory.as_ref()
+    }
+
+    pub fn output(&self) -> Option<&Vec<W>> {
         match self.output {
             OutputMode::Buffer(ref buf) => Some(buf),
             _ => None,
         }
     }
 
-    pub fn output_mut(&mut self) -> Option<&mut Vec<Int>> {
+    pub fn output_mut(&mut self) -> Option<&mut Vec<W>> {
         match self.output {
             OutputMode::Buffer(ref mut buf) => Some(buf),
             _ => None,
         }
     }
 
-    fn write_output(&mut self, value: Int) {
+    pub fn trace(&self) -> Option<&[TraceEntry<W>]> {
+        self.trace.as_deref()
+    }
+
+    /// Returns the recorded trace, leaving an empty one to keep recording into.
+    pub fn take_trace(&mut self) -> Option<Vec<TraceEntry<W>>> {
+        self.trace.as_mut().map(std::mem::take)
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    fn profile_step(&mut self, ip: usize, instruction: &Instruction, args: &[W]) {
+        let reads: Vec<usize> = (0..instruction.arg_len())
+            .filter(|&i| {
+                instruction.modes[i] != ParameterMode::Immediate
+                    && instruction.output_arg() != Some(i)
+            })
+            .filter_map(|i| self.eval_address(instruction, args, i).ok()?.to_address())
+            .collect();
+        if let Some(profile) = self.profile.as_mut() {
+            profile.record_step(ip, instruction.opcode, &reads);
+        }
+    }
+
+    fn trace_entry(&mut self) -> Option<&mut TraceEntry<W>> {
+        self.trace.as_mut()?.last_mut()
+    }
+
+    /// Writes to memory as part of executing an instruction, recording it in the trace.
+    fn store(&mut self, value: W, address: W) -> Result<(), AddressError> {
+        let index = address.to_address().ok_or(AddressError::OutOfRange)?;
+        // Only copy the old and new values when there's a trace to put them in
+        let traced = self
+            .trace
+            .is_some()
+            .then(|| (self.read_memory(index), value.clone()));
+        self.write_memory(value, address)?;
+        if let Some(profile) = self.profile.as_mut() {
+            profile.record_write(index);
+        }
+        if let (Some((Ok(old), new)), Some(entry)) = (traced, self.trace_entry()) {
+            entry.writes.push(MemoryWrite {
+                address: index,
+                old,
+                new,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns `false` if the output has nowhere to go.
+    fn write_output(&mut self, value: W) -> bool {
         match self.output {
             OutputMode::Stdout => println!("{}", value),
             OutputMode::Buffer(ref mut buf) => buf.push(value),
+            OutputMode::AsciiStdout => match ascii_char(&value) {
+                Some(c) => print!("{}", c),
+                None => println!("{}", value),
+            },
+            OutputMode::Channel(ref sender) => return sender.send(value).is_ok(),
+            OutputMode::Device(ref device) => return device.lock().unwrap().output(value),
         }
+        true
+    }
+
+    /// Returns the output buffer decoded as text.
+    pub fn output_ascii(&self) -> Option<Vec<AsciiOutput<W>>> {
+        self.output().map(|output| decode_ascii(output))
     }
 
     pub fn step(&mut self) -> Status {
+        let started = self.profile.is_some().then(Instant::now);
         if self.status.is_ready() {
+            let ip = self.ip;
             // Program has finished executing
             if let Ok(instr) = self.next_instruction() {
                 // Consume arguments
                 if let Ok(args) = self.consume_args(&instr) {
-                    self.status = match instr.opcode {
-                        Opcode::Add => {
-                            let sum =
-                                self.eval_arg(&instr, &args, 0) + self.eval_arg(&instr, &args, 1);
-                            self.write_memory(sum, args[2]);
-                            Status::Ready
-                        }
-                        Opcode::Mul => {
-                            let product =
-                                self.eval_arg(&instr, &args, 0) * self.eval_arg(&instr, &args, 1);
-                            self.write_memory(product, args[2]);
-                            Status::Ready
-                        }
-                        Opcode::Str => {
-                            if let Some(value) = self.take_input() {
-                                self.write_memory(value, args[0]);
-                                Status::Ready
-                            } else {
-                                Status::Error
-                            }
-                        }
-                        Opcode::Out => {
-                            self.write_output(self.eval_arg(&instr, &args, 0));
-                            Status::Ready
-                        }
-                        Opcode::Bot => {
-                            if self.eval_arg(&instr, &args, 0).into() {
-                                if let Ok(ptr) = self.eval_arg(&instr, &args, 1).try_into() {
-                                    self.ip = ptr;
-                                    Status::Ready
-                                } else {
-                                    Status::Error
-                                }
-                            } else {
-                                Status::Ready
-                            }
-                        }
-                        Opcode::Bof => {
-                            if !bool::from(self.eval_arg(&instr, &args, 0)) {
-                                if let Ok(ptr) = self.eval_arg(&instr, &args, 1).try_into() {
-                                    self.ip = ptr;
-                                    Status::Ready
+                    let entry = self.trace.is_some().then(|| TraceEntry {
+                        ip,
+                        instruction: instr,
+                        // Operands that can't be evaluated are recorded as written
+                        operands: (0..instr.arg_len())
+                            .map(|i| {
+                                if instr.output_arg() == Some(i) {
+                                    self.eval_address(&instr, &args, i)
                                 } else {
-                                    Status::Error
+                                    self.eval_arg(&instr, &args, i)
                                 }
-                            } else {
-                                Status::Ready
-                            }
-                        }
-                        Opcode::Clt => {
-                            let cmp =
-                                self.eval_arg(&instr, &args, 0) < self.eval_arg(&instr, &args, 1);
-                            self.write_memory(cmp.into(), args[2]);
-                            Status::Ready
-                        }
-                        Opcode::Ceq => {
-                            let cmp =
-                                self.eval_arg(&instr, &args, 0) == self.eval_arg(&instr, &args, 1);
-                            self.write_memory(cmp.into(), args[2]);
-                            Status::Ready
-                        }
-                        Opcode::Hlt => Status::Finished,
+                                .unwrap_or_else(|_| args[i].clone())
+                            })
+                            .collect(),
+                        writes: Vec::new(),
+                        input: None,
+                        output: None,
+                    });
+                    if let (Some(trace), Some(entry)) = (self.trace.as_mut(), entry) {
+                        trace.push(entry);
                     }
+                    if self.profile.is_some() {
+                        self.profile_step(ip, &instr, &args);
+                    }
+                    self.status = self
+                        .run_instruction(ip, &instr, &args)
+                        .unwrap_or_else(|error| error.status(ip));
                 } else {
                     self.status = Status::Error;
                 }
@@ -553,21 +1170,132 @@ impl IntTerpreter {
                 self.status = Status::Finished;
             }
         }
+        if let (Some(profile), Some(started)) = (self.profile.as_mut(), started) {
+            profile.record_time(started.elapsed());
+        }
         self.status
     }
 
+    /// Fails if the instruction uses an address it can't get to.
+    fn run_instruction(
+        &mut self,
+        ip: usize,
+        instr: &Instruction,
+        args: &[W],
+    ) -> Result<Status, AddressError> {
+        Ok(match instr.opcode {
+            Opcode::Add => {
+                let a = self.eval_arg(instr, args, 0)?;
+                let b = self.eval_arg(instr, args, 1)?;
+                if let Some(sum) = a.checked_add(&b) {
+                    self.store(sum, self.eval_address(instr, args, 2)?)?;
+                    Status::Ready
+                } else {
+                    Status::Overflow(ip)
+                }
+            }
+            Opcode::Mul => {
+                let a = self.eval_arg(instr, args, 0)?;
+                let b = self.eval_arg(instr, args, 1)?;
+                if let Some(product) = a.checked_mul(&b) {
+                    self.store(product, self.eval_address(instr, args, 2)?)?;
+                    Status::Ready
+                } else {
+                    Status::Overflow(ip)
+                }
+            }
+            Opcode::Str => {
+                if let Some(value) = self.take_input() {
+                    if let Some(entry) = self.trace_entry() {
+                        entry.input = Some(value.clone());
+                    }
+                    self.store(value, self.eval_address(instr, args, 0)?)?;
+                    Status::Ready
+                } else if matches!(self.input, InputMode::Channel(..) | InputMode::Device(_)) {
+                    Status::Stopped
+                } else {
+                    Status::Error
+                }
+            }
+            Opcode::Out => {
+                let value = self.eval_arg(instr, args, 0)?;
+                if let Some(entry) = self.trace_entry() {
+                    entry.output = Some(value.clone());
+                }
+                if self.write_output(value) {
+                    Status::Ready
+                } else {
+                    Status::Stopped
+                }
+            }
+            Opcode::Bot => {
+                if !self.eval_arg(instr, args, 0)?.is_zero() {
+                    if let Some(ptr) = self.eval_arg(instr, args, 1)?.to_address() {
+                        self.ip = ptr;
+                        Status::Ready
+                    } else {
+                        Status::Error
+                    }
+                } else {
+                    Status::Ready
+                }
+            }
+            Opcode::Bof => {
+                if self.eval_arg(instr, args, 0)?.is_zero() {
+                    if let Some(ptr) = self.eval_arg(instr, args, 1)?.to_address() {
+                        self.ip = ptr;
+                        Status::Ready
+                    } else {
+                        Status::Error
+                    }
+                } else {
+                    Status::Ready
+                }
+            }
+            Opcode::Clt => {
+                let cmp = self.eval_arg(instr, args, 0)? < self.eval_arg(instr, args, 1)?;
+                self.store(W::from_i64(cmp as i64), self.eval_address(instr, args, 2)?)?;
+                Status::Ready
+            }
+            Opcode::Ceq => {
+                let cmp = self.eval_arg(instr, args, 0)? == self.eval_arg(instr, args, 1)?;
+                self.store(W::from_i64(cmp as i64), self.eval_address(instr, args, 2)?)?;
+                Status::Ready
+            }
+            Opcode::Arb => {
+                let offset = self.eval_arg(instr, args, 0)?;
+                if let Some(base) = self.relative_base.checked_add(&offset) {
+                    self.relative_base = base;
+                    Status::Ready
+                } else {
+                    Status::Overflow(ip)
+                }
+            }
+            Opcode::Hlt => Status::Finished,
+        })
+    }
+
     pub fn execute(&mut self) -> Status {
         while self.step().is_ready() {}
         self.status
     }
 
+    /// Executes the program on its own thread. Any channels it uses for input and output are
+    /// closed once it stops.
+    pub fn spawn(mut self) -> JoinHandle<Status>
+    where
+        W: Send,
+    {
+        std::thread::spawn(move || self.execute())
+    }
+
     pub fn execute_until_output(&mut self) -> Status {
         let output_len = self.output().unwrap().len();
         while self.step().is_ready() && output_len == self.output().unwrap().len() {}
         self.status
     }
 
-    pub fn get(&self, index: usize) -> Option<Int> {
+    pub fn get(&self, index: usize) -> Option<W> {
         if let Some(memory) = &self.memory {
             memory.get(index)
         } else {
@@ -579,6 +1307,7 @@ impl IntTerpreter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::assembler::assemble;
 
     #[test]
     fn test_general() {
@@ -603,7 +1332,7 @@ mod tests {
             ("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99", 7, 999),
             ("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99", 9, 1001),
         ] {
-            let memory = Memory::try_from(program).unwrap();
+            let memory: Memory = Memory::try_from(program).unwrap();
             let mut interpreter = IntTerpreter::new()
                 .with_memory(memory)
                 .with_input(&[input])
@@ -614,6 +1343,235 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ascii() {
+        // Echoes two characters, then outputs a value too big to be one
+        let memory = Memory::try_from("3,0,4,0,3,0,4,0,104,1000,99").unwrap();
+        let mut interpreter = IntTerpreter::new()
+            .with_memory(memory)
+            .with_ascii_input(&["h"])
+            .with_buffer();
+        interpreter.execute();
+        assert_eq!(
+            interpreter.output_ascii().unwrap(),
+            [
+                AsciiOutput::Text("h\n".to_string()),
+                AsciiOutput::Raw(Int(1000))
+            ]
+        );
+
+        assert_eq!(encode_ascii::<Int>("go"), [Int(103), Int(111), Int(10)]);
+        assert_eq!(
+            decode_ascii(&[Int(-1), Int(72), Int(105), Int(128)]),
+            [
+                AsciiOutput::Raw(Int(-1)),
+                AsciiOutput::Text("Hi".to_string()),
+                AsciiOutput::Raw(Int(128))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_channels() {
+        let (input, receiver) = crossbeam_channel::unbounded();
+        let (sender, output) = crossbeam_channel::unbounded();
+        let handle = IntTerpreter::new()
+            .with_memory(Memory::try_from("3,7,4,7,1105,1,0,0").unwrap())
+            .with_channel_input(receiver, None)
+            .with_channel_output(sender)
+            .spawn();
+
+        // Echoes everything until its input is closed
+        for i in 0..5 {
+            input.send(Int(i)).unwrap();
+            assert_eq!(output.recv(), Ok(Int(i)));
+        }
+        drop(input);
+        assert_eq!(handle.join().unwrap(), Status::Stopped);
+        assert!(output.recv().is_err());
+
+        let (_input, receiver) = crossbeam_channel::unbounded();
+        let mut interpreter = IntTerpreter::<Int>::new()
+            .with_memory(Memory::try_from("3,0,99").unwrap())
+            .with_channel_input(receiver, Some(Duration::from_millis(10)));
+        assert_eq!(interpreter.execute(), Status::Stopped);
+
+        // Clones share their input channel, so only one of them gets each value
+        let (input, receiver) = crossbeam_channel::unbounded();
+        let mut interpreter = IntTerpreter::new()
+            .with_memory(Memory::try_from("3,0,99").unwrap())
+            .with_channel_input(receiver, Some(Duration::from_millis(10)));
+        let mut fork = interpreter.clone();
+        input.send(Int(7)).unwrap();
+        assert_eq!(interpreter.execute(), Status::Finished);
+        assert_eq!(fork.execute(), Status::Stopped);
+        assert_eq!(interpreter.get(0), Some(Int(7)));
+        assert_eq!(fork.get(0), Some(Int(3)));
+    }
+
+    #[test]
+    fn test_memory_pages() {
+        let words: Vec<Int> = (0..PAGE_SIZE as i64 * 3).map(Int).collect();
+        let memory = Memory::from(words.clone());
+        let mut copy = memory.clone();
+        copy[PAGE_SIZE + 1] = Int(-1);
+
+        // Only the page that was written to gets copied
+        assert!(Arc::ptr_eq(&memory.pages[0], &copy.pages[0]));
+        assert!(!Arc::ptr_eq(&memory.pages[1], &copy.pages[1]));
+        assert!(Arc::ptr_eq(&memory.pages[2], &copy.pages[2]));
+        assert_eq!(memory[PAGE_SIZE + 1], Int(PAGE_SIZE as i64 + 1));
+        assert_eq!(copy.get(PAGE_SIZE + 1), Some(Int(-1)));
+        assert_eq!(copy.get(PAGE_SIZE * 3), None);
+        assert_eq!(Vec::from(memory), words);
+    }
+
+    #[test]
+    fn test_decode_cache() {
+        // Outputs `v`, then rewrites its first instruction to output `v`'s address instead
+        let memory = assemble(
+            "
+                  out [v]
+                  eq  [done], #1, [t]
+                  jt  [t], #end
+                  add #1, #0, [done]
+                  add #104, #0, [0]
+                  jt  #1, #0
+            end:  hlt
+            v:    .data 42
+            done: .data 0
+            t:    .data 0
+            ",
+        )
+        .unwrap();
+        for interpreter in [IntTerpreter::new(), IntTerpreter::new().with_decode_cache()] {
+            let mut interpreter = interpreter.with_memory(memory.clone()).with_buffer();
+            assert_eq!(interpreter.execute(), Status::Finished);
+            assert_eq!(interpreter.output().unwrap(), &[Int(42), Int(21)]);
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        // Keeps multiplying its input by a million until something gives
+        let program = "3,11,1002,11,1000000,11,4,11,1105,1,2,0";
+        let mut interpreter = IntTerpreter::new()
+            .with_memory(Memory::try_from(program).unwrap())
+            .with_input(&[1])
+            .with_buffer();
+        assert_eq!(interpreter.execute(), Status::Overflow(2));
+        assert_eq!(
+            interpreter.output().unwrap().last(),
+            Some(&Int(1_000_000_000_000_000_000))
+        );
+
+        // Arbitrary precision words just keep going
+        let mut interpreter = IntTerpreter::<BigInt>::new()
+            .with_memory(Memory::try_from(program).unwrap())
+            .with_input(&[1])
+            .with_buffer();
+        while interpreter.output().unwrap().len() < 5 {
+            assert!(interpreter.step().is_ready());
+        }
+        assert_eq!(
+            interpreter.output().unwrap().last().unwrap().to_string(),
+            format!("1{}", "0".repeat(30))
+        );
+    }
+
+    #[test]
+    fn test_snapshot() {
+        // Adds up inputs until it's given a zero
+        let memory = Memory::try_from("3,13,1006,13,12,1,13,14,14,1105,1,0,99,0,0").unwrap();
+        let mut interpreter = IntTerpreter::new()
+            .with_memory(memory.clone())
+            .with_input(&[1, 2])
+            .with_buffer();
+        let run_until_blocked = |interpreter: &mut IntTerpreter| {
+            while !interpreter.needs_input() && interpreter.step().is_ready() {}
+        };
+        run_until_blocked(&mut interpreter);
+        assert!(interpreter.needs_input());
+
+        let snapshot = interpreter.snapshot();
+        let mut fork = interpreter.clone();
+        interpreter.input_mut().unwrap().extend([Int(3), Int(0)]);
+        interpreter.execute();
+        assert_eq!(interpreter.status(), Status::Finished);
+        assert_eq!(interpreter.get(14), Some(Int(6)));
+
+        // Neither the snapshot nor the clone see what happened after they were taken
+        assert_eq!(snapshot.memory.as_ref().unwrap()[14], Int(3));
+        assert_eq!(fork.get(14), Some(Int(3)));
+        assert_eq!(memory[14], Int(0));
+
+        let path =
+            std::env::temp_dir().join(format!("intcode-snapshot-{}.json", std::process::id()));
+        snapshot.save(&path).unwrap();
+        let loaded = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        interpreter.restore(loaded);
+        interpreter.input_mut().unwrap().extend([Int(10), Int(0)]);
+        interpreter.execute();
+        assert_eq!(interpreter.get(14), Some(Int(13)));
+
+        run_until_blocked(&mut fork);
+        fork.input_mut().unwrap().push(Int(0));
+        fork.execute();
+        assert_eq!(fork.get(14), Some(Int(3)));
+    }
+
+    #[test]
+    fn test_relative_base() {
+        // Moves the base around, then reads, writes and outputs relative to it
+        let memory = assemble(
+            "
+                arb #value
+                add rb, #1, rb+1
+                arb #-2
+                out rb+3
+                hlt
+            value: .data 41, 0
+            ",
+        )
+        .unwrap();
+        let mut interpreter = IntTerpreter::new().with_memory(memory).with_buffer();
+        assert_eq!(interpreter.execute(), Status::Finished);
+        assert_eq!(interpreter.output().unwrap(), &[Int(42)]);
+        assert_eq!(interpreter.get(12), Some(Int(42)));
+
+        let memory = Memory::<Int>::try_from("109,9223372036854775807,109,1,99").unwrap();
+        let mut interpreter = IntTerpreter::new().with_memory(memory);
+        assert_eq!(interpreter.execute(), Status::Overflow(2));
+    }
+
+    #[test]
+    fn test_bad_addresses() {
+        for (program, status) in [
+            ("4,100,99", Status::BadAddress(0)),
+            ("4,-1,99", Status::BadAddress(0)),
+            ("1101,1,1,50,99", Status::BadAddress(0)),
+            ("109,9223372036854775807,204,1,99", Status::Overflow(2)),
+            ("109,-1,209,0,99", Status::BadAddress(2)),
+        ] {
+            let mut interpreter = IntTerpreter::<Int>::new()
+                .with_memory(Memory::try_from(program).unwrap())
+                .with_buffer();
+            assert_eq!(interpreter.execute(), status, "{}", program);
+        }
+
+        // Writing outside of memory fails instead of growing it
+        let mut interpreter =
+            IntTerpreter::new().with_memory(Memory::<Int>::try_from("99").unwrap());
+        assert_eq!(
+            interpreter.write_memory(Int(1), Int(1)),
+            Err(AddressError::OutOfRange)
+        );
+        assert_eq!(interpreter.write_memory(Int(1), Int(0)), Ok(()));
+    }
+
     fn run_program(raw_program: &str) -> Memory {
         let memory = Memory::try_from(raw_program).unwrap();
         let mut interpreter = IntTerpreter::new().with_memory(memory);
@@ -626,6 +1584,7 @@ mod tests {
     fn test_instruction() {
         let n = Int(1002);
         let instr = Instruction::try_from(n).unwrap();
+        assert_eq!(Int::from(instr), n);
         assert_eq!(instr.opcode, Opcode::Mul);
         assert_eq!(
             instr.modes,
@@ -635,5 +1594,9 @@ mod tests {
                 ParameterMode::Position
             ]
         );
+
+        for n in [-1, 0, 10, 301, 100_001] {
+            assert!(Instruction::try_from(Int(n)).is_err());
+        }
     }
 }
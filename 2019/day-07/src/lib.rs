@@ -1,56 +1,131 @@
-use intterpreter::{IntTerpreter, Memory};
+use std::time::Duration;
+
+use crossbeam_channel::unbounded;
+use intterpreter::{Int, IntTerpreter, Memory, Status};
 use itertools::Itertools;
+use network::{Network, NetworkStatus, Topology};
+use rayon::prelude::*;
 
+pub mod assembler;
+pub mod control_flow;
+pub mod debugger;
+pub mod device;
+pub mod disassembler;
 pub mod intterpreter;
+pub mod network;
+pub mod profile;
+pub mod trace;
 
 pub fn part_one(input: &'static str) -> i64 {
     let memory = parse_input(input);
-    (0..=4)
-        .permutations(5)
-        .map(|sequence| {
-            let mut input = 0;
-            for phase_setting in sequence {
-                let mut computer = IntTerpreter::new()
-                    .with_memory(memory.clone())
-                    .with_input(&[phase_setting, input])
-                    .with_buffer();
-                computer.execute();
-                input = computer.output().unwrap().last().unwrap().into();
-            }
-            input
-        })
-        .max()
+    search_phases(&memory, &[0, 1, 2, 3, 4], 5, Wiring::Serial)
         .unwrap()
+        .signal
 }
 
 pub fn part_two(input: &'static str) -> i64 {
     let memory = parse_input(input);
-    (5..=9)
-        .permutations(5)
-        .map(|sequence| {
-            let mut input = 0;
-            let mut computers = sequence
-                .into_iter()
-                .map(|phase_setting| {
-                    IntTerpreter::new()
-                        .with_memory(memory.clone())
-                        .with_input(&[phase_setting])
-                        .with_buffer()
-                })
-                .collect_vec();
-
-            while computers[0].status().is_ready() {
-                for computer in computers.iter_mut() {
-                    computer.input_mut().unwrap().push(input.into());
-                    computer.execute_until_output();
-                    input = computer.output().unwrap().last().unwrap().into();
-                }
-            }
-            *computers[4].output().unwrap().last().unwrap()
-        })
-        .max()
+    search_phases(&memory, &[5, 6, 7, 8, 9], 5, Wiring::Feedback)
         .unwrap()
-        .into()
+        .signal
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Wiring {
+    /// Each amplifier feeds the next, and the last one produces the signal.
+    Serial,
+    /// Like [`Serial`], but the last amplifier also feeds back into the first until they all
+    /// halt.
+    ///
+    /// [`Serial`]: Wiring::Serial
+    Feedback,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhaseSearch {
+    pub sequence: Vec<i64>,
+    pub signal: i64,
+}
+
+/// Tries every way of giving `stages` amplifiers distinct phase settings from `phases`,
+/// returning the sequence that produces the highest signal. Ties go to the sequence that comes
+/// first.
+pub fn search_phases(
+    memory: &Memory,
+    phases: &[i64],
+    stages: usize,
+    wiring: Wiring,
+) -> Option<PhaseSearch> {
+    if stages == 0 {
+        return None;
+    }
+    phases
+        .iter()
+        .copied()
+        .permutations(stages)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .filter_map(|sequence| {
+            let signal = match wiring {
+                Wiring::Serial => amplify(memory, &sequence, Topology::Chain),
+                Wiring::Feedback => amplify(memory, &sequence, Topology::Ring),
+            }?;
+            Some(PhaseSearch { sequence, signal })
+        })
+        .max_by(|a, b| {
+            a.signal
+                .cmp(&b.signal)
+                .then_with(|| b.sequence.cmp(&a.sequence))
+        })
+}
+
+/// Runs a network of amplifiers with the given phase settings, returning the final signal. Gives
+/// up if the amplifiers get stuck waiting on each other.
+fn amplify(memory: &Memory, sequence: &[i64], topology: Topology) -> Option<i64> {
+    let mut network = Network::new(memory, sequence.len(), topology);
+    for (i, &phase_setting) in sequence.iter().enumerate() {
+        network.send(i, phase_setting);
+    }
+    network.send(0, 0);
+    match network.run() {
+        NetworkStatus::Halted => network.last_output(sequence.len() - 1).map(i64::from),
+        NetworkStatus::Idle => None,
+    }
+}
+
+/// Runs a feedback loop of amplifiers with each one on its own thread, returning the final
+/// signal. Amplifiers that wait longer than `timeout` for input shut down, and the loop gives up.
+pub fn amplify_threaded(memory: &Memory, sequence: &[i64], timeout: Duration) -> Option<i64> {
+    let (feedback, mut receiver) = unbounded();
+    feedback.send(Int::new(*sequence.first()?)).unwrap();
+    feedback.send(Int::new(0)).unwrap();
+
+    let mut handles = Vec::new();
+    for i in 0..sequence.len() {
+        let (sender, next) = unbounded();
+        if let Some(&phase_setting) = sequence.get(i + 1) {
+            sender.send(Int::new(phase_setting)).unwrap();
+        }
+        let amplifier = IntTerpreter::new()
+            .with_memory(memory.clone())
+            .with_channel_input(std::mem::replace(&mut receiver, next), Some(timeout))
+            .with_channel_output(sender);
+        handles.push(amplifier.spawn());
+    }
+
+    // The last amplifier's output goes through here so that the final signal can be seen
+    let mut signal = None;
+    for value in receiver {
+        signal = Some(value);
+        // The first amplifier has halted once it stops listening
+        let _ = feedback.send(value);
+    }
+    drop(feedback);
+    let mut finished = true;
+    for handle in handles {
+        finished &= handle.join().unwrap() == Status::Finished;
+    }
+    signal.filter(|_| finished).map(i64::from)
 }
 
 fn parse_input(input: &'static str) -> Memory {
@@ -59,7 +134,7 @@ fn parse_input(input: &'static str) -> Memory {
 
 #[cfg(test)]
 mod tests {
-    use super::{part_one, part_two};
+    use super::*;
 
     #[test]
     fn test_part_one() {
@@ -81,4 +156,61 @@ mod tests {
             assert_eq!(part_two(program), result);
         }
     }
+
+    #[test]
+    fn test_amplify_threaded() {
+        let timeout = Duration::from_millis(100);
+        let memory = parse_input(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        );
+        assert_eq!(
+            amplify_threaded(&memory, &[9, 8, 7, 6, 5], timeout),
+            Some(139629729)
+        );
+        assert_eq!(amplify_threaded(&memory, &[], timeout), None);
+
+        // Waits on input forever unless its phase setting is non-zero
+        let memory = parse_input("3,11,1005,11,10,3,11,1105,1,5,99,0");
+        assert_eq!(amplify_threaded(&memory, &[0, 1], timeout), None);
+        assert_eq!(amplify_threaded(&memory, &[1, 0], timeout), None);
+    }
+
+    #[test]
+    fn test_search_phases() {
+        let memory = parse_input("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+        assert_eq!(
+            search_phases(&memory, &[0, 1, 2, 3, 4], 5, Wiring::Serial),
+            Some(PhaseSearch {
+                sequence: vec![4, 3, 2, 1, 0],
+                signal: 43210
+            })
+        );
+        assert_eq!(
+            search_phases(&memory, &[0, 1, 2, 3, 4, 5, 6], 3, Wiring::Serial),
+            Some(PhaseSearch {
+                sequence: vec![6, 5, 4],
+                signal: 654
+            })
+        );
+        assert_eq!(search_phases(&memory, &[1, 2], 3, Wiring::Serial), None);
+
+        let memory = parse_input(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        );
+        assert_eq!(
+            search_phases(&memory, &[5, 6, 7, 8, 9], 5, Wiring::Feedback),
+            Some(PhaseSearch {
+                sequence: vec![9, 8, 7, 6, 5],
+                signal: 139629729
+            })
+        );
+
+        // Each amplifier wants a third input that never comes
+        let memory = parse_input("3,0,3,0,3,0,99");
+        assert_eq!(search_phases(&memory, &[0, 1], 2, Wiring::Feedback), None);
+
+        // Halting with input still unread doesn't keep the loop going
+        let memory = parse_input("3,11,1005,11,10,3,11,1105,1,5,99,0");
+        assert_eq!(search_phases(&memory, &[0, 1], 2, Wiring::Feedback), None);
+    }
 }
@@ -0,0 +1,327 @@
+use crate::intterpreter::{Int, IntTerpreter, Memory};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Topology {
+    /// Each machine's output feeds the next one, and the last machine outputs to the network.
+    Chain,
+    /// Each machine's output feeds the next one, and the last machine feeds the first.
+    Ring,
+    /// Machine `i`'s output is copied to every machine listed in `edges[i]`. Machines without
+    /// any edges output to the network.
+    Graph(Vec<Vec<usize>>),
+    /// Machines output `(dest, x, y)` triples, which are delivered to the input of machine
+    /// `dest`. Packets addressed to any other machine end up in the network's outbox, and
+    /// reading from an empty input gives `-1` rather than blocking.
+    Packets,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Packet {
+    pub dest: Int,
+    pub x: Int,
+    pub y: Int,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkStatus {
+    /// Every machine has halted.
+    Halted,
+    /// Every running machine is waiting on input that nothing is going to send.
+    Idle,
+}
+
+/// What a single machine did with its turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Turn {
+    Output,
+    Blocked,
+    Polled,
+    Halted,
+}
+
+#[derive(Debug)]
+pub struct Network {
+    machines: Vec<IntTerpreter>,
+    topology: Topology,
+    last_outputs: Vec<Option<Int>>,
+    output: Vec<Int>,
+    outbox: Vec<Packet>,
+    idle: bool,
+}
+
+impl Network {
+    pub fn new(memory: &Memory, machines: usize, topology: Topology) -> Self {
+        Self {
+            machines: (0..machines)
+                .map(|_| {
+                    IntTerpreter::new()
+                        .with_memory(memory.clone())
+                        .with_input::<i64>(&[])
+                        .with_buffer()
+                })
+                .collect(),
+            topology,
+            last_outputs: vec![None; machines],
+            output: Vec::new(),
+            outbox: Vec::new(),
+            idle: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    pub fn machine(&self, index: usize) -> &IntTerpreter {
+        &self.machines[index]
+    }
+
+    pub fn machine_mut(&mut self, index: usize) -> &mut IntTerpreter {
+        &mut self.machines[index]
+    }
+
+    /// Queues a value on the input of a machine.
+    pub fn send<T>(&mut self, index: usize, value: T)
+    where
+        Int: From<T>,
+    {
+        self.machines[index]
+            .input_mut()
+            .unwrap()
+            .push(Int::from(value));
+        self.idle = false;
+    }
+
+    /// Delivers a packet as if one of the machines had sent it.
+    pub fn send_packet(&mut self, packet: Packet) {
+        self.route_packet(packet);
+        self.idle = false;
+    }
+
+    /// Returns the values output to the network by machines that don't feed any others.
+    pub fn output(&self) -> &[Int] {
+        &self.output
+    }
+
+    /// Returns the most recent value output by a machine, wherever it was sent.
+    pub fn last_output(&self, index: usize) -> Option<Int> {
+        self.last_outputs[index]
+    }
+
+    /// Removes and returns the packets addressed outside of the network.
+    pub fn take_outbox(&mut self) -> Vec<Packet> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    /// Returns `true` if the last round ended with every running machine waiting on input
+    /// and nothing left in flight.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.machines
+            .iter()
+            .all(|machine| !machine.status().is_ready())
+    }
+
+    fn targets(&self, index: usize) -> Vec<usize> {
+        let n = self.machines.len();
+        match &self.topology {
+            Topology::Chain if index + 1 < n => vec![index + 1],
+            Topology::Chain => Vec::new(),
+            Topology::Ring => vec![(index + 1) % n],
+            Topology::Graph(edges) => edges.get(index).cloned().unwrap_or_default(),
+            Topology::Packets => unreachable!(),
+        }
+    }
+
+    fn route_packet(&mut self, packet: Packet) {
+        match usize::try_from(packet.dest)
+            .ok()
+            .and_then(|dest| self.machines.get_mut(dest))
+        {
+            Some(machine) => machine.input_mut().unwrap().extend([packet.x, packet.y]),
+            None => self.outbox.push(packet),
+        }
+    }
+
+    /// Moves everything a machine has output to wherever it's going.
+    fn route(&mut self, index: usize) {
+        let output = self.machines[index].output_mut().unwrap();
+        if self.topology == Topology::Packets {
+            let complete = output.len() - output.len() % 3;
+            let values: Vec<Int> = output.drain(..complete).collect();
+            for triple in values.chunks(3) {
+                self.route_packet(Packet {
+                    dest: triple[0],
+                    x: triple[1],
+                    y: triple[2],
+                });
+            }
+        } else {
+            let values = std::mem::take(output);
+            let targets = self.targets(index);
+            for value in values {
+                if targets.is_empty() {
+                    self.output.push(value);
+                }
+                for &target in &targets {
+                    self.machines[target].input_mut().unwrap().push(value);
+                }
+            }
+        }
+    }
+
+    /// Runs a machine until it outputs something or can't make any more progress.
+    fn take_turn(&mut self, index: usize) -> Turn {
+        let packets = self.topology == Topology::Packets;
+        let machine = &mut self.machines[index];
+        loop {
+            if !machine.status().is_ready() {
+                return Turn::Halted;
+            }
+            if machine.needs_input() {
+                if !packets {
+                    return Turn::Blocked;
+                }
+                machine.input_mut().unwrap().push(Int::new(-1));
+                machine.step();
+                return Turn::Polled;
+            }
+
+            let output_len = machine.output().unwrap().len();
+            machine.step();
+            let output = machine.output().unwrap();
+            if output.len() > output_len {
+                self.last_outputs[index] = output.last().copied();
+                // Packets only go out once they're complete
+                if !packets || output.len().is_multiple_of(3) {
+                    return Turn::Output;
+                }
+            }
+        }
+    }
+
+    /// Gives every machine a turn, returning `true` if anything happened. Input left waiting on
+    /// a machine that has halted doesn't count.
+    pub fn round(&mut self) -> bool {
+        let mut progress = false;
+        for index in 0..self.machines.len() {
+            if !self.machines[index].status().is_ready() {
+                continue;
+            }
+            let queued = self.machines[index].intput().unwrap().len();
+            let turn = self.take_turn(index);
+            let consumed = self.machines[index].intput().unwrap().len() < queued;
+            self.route(index);
+            progress |= consumed || turn == Turn::Output;
+        }
+        self.idle = !progress && !self.is_halted();
+        progress
+    }
+
+    /// Runs rounds until every machine halts or the network goes idle.
+    pub fn run(&mut self) -> NetworkStatus {
+        loop {
+            self.round();
+            if self.is_halted() {
+                return NetworkStatus::Halted;
+            }
+            if self.idle {
+                return NetworkStatus::Idle;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+
+    #[test]
+    fn test_graph() {
+        let memory = assemble(
+            "
+                in  [value]
+                mul [value], #2, [value]
+                out [value]
+                hlt
+            value: .data 0
+            ",
+        )
+        .unwrap();
+        let mut network = Network::new(&memory, 4, Topology::Graph(vec![vec![1, 2], vec![3]]));
+        network.send(0, 5);
+        assert_eq!(network.run(), NetworkStatus::Halted);
+        assert_eq!(network.output(), [Int::new(20), Int::new(40)]);
+        assert_eq!(network.last_output(1), Some(Int::new(20)));
+    }
+
+    #[test]
+    fn test_ring_idle() {
+        // Everyone waits on their neighbour, so nothing ever happens
+        let memory = assemble("in [0]\nhlt").unwrap();
+        let mut network = Network::new(&memory, 3, Topology::Ring);
+        assert_eq!(network.run(), NetworkStatus::Idle);
+        assert!(network.is_idle());
+
+        network.send(1, 0);
+        assert!(!network.is_idle());
+        network.round();
+        assert!(network.machine(1).status().is_finished());
+        assert_eq!(network.run(), NetworkStatus::Idle);
+    }
+
+    #[test]
+    fn test_packets() {
+        // Sends a packet to the next machine over, then forwards anything it receives
+        let memory = assemble(
+            "
+                  in  [addr]
+                  add [addr], #1, [dest]
+                  out [dest]
+                  out [addr]
+                  out #100
+            loop: in  [x]
+                  eq  [x], #-1, [t]
+                  jt  [t], #loop
+                  in  [y]
+                  add [x], #1, [x]
+                  out [dest]
+                  out [x]
+                  out [y]
+                  jt  #1, #loop
+            addr: .data 0
+            dest: .data 0
+            x:    .data 0
+            y:    .data 0
+            t:    .data 0
+            ",
+        )
+        .unwrap();
+        let mut network = Network::new(&memory, 3, Topology::Packets);
+        for i in 0..3 {
+            network.send(i, i as i64);
+        }
+
+        let packet = |dest, x, y| Packet {
+            dest: Int::new(dest),
+            x: Int::new(x),
+            y: Int::new(y),
+        };
+        assert_eq!(network.run(), NetworkStatus::Idle);
+        assert_eq!(network.take_outbox(), vec![packet(3, 2, 100); 3]);
+
+        // Act like a NAT, waking the network back up once it's gone quiet
+        network.send_packet(packet(0, 5, 7));
+        assert!(!network.is_idle());
+        assert_eq!(network.run(), NetworkStatus::Idle);
+        assert_eq!(network.take_outbox(), vec![packet(3, 8, 7)]);
+    }
+}
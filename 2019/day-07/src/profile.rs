@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::disassembler::Disassembler;
+use crate::intterpreter::{Memory, Opcode};
+
+/// Counts of where a program spends its time, recorded while it runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    executions: Vec<u64>,
+    reads: Vec<u64>,
+    writes: Vec<u64>,
+    opcodes: HashMap<Opcode, u64>,
+    steps: u64,
+    elapsed: Duration,
+}
+
+fn bump(counts: &mut Vec<u64>, address: usize) {
+    if counts.len() <= address {
+        counts.resize(address + 1, 0);
+    }
+    counts[address] += 1;
+}
+
+impl Profile {
+    pub(crate) fn record_step(&mut self, address: usize, opcode: Opcode, reads: &[usize]) {
+        bump(&mut self.executions, address);
+        *self.opcodes.entry(opcode).or_default() += 1;
+        for &read in reads {
+            bump(&mut self.reads, read);
+        }
+        self.steps += 1;
+    }
+
+    pub(crate) fn record_write(&mut self, address: usize) {
+        bump(&mut self.writes, address);
+    }
+
+    pub(crate) fn record_time(&mut self, elapsed: Duration) {
+        self.elapsed += elapsed;
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Returns the time spent executing instructions.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns how many times the instruction at `address` was executed.
+    pub fn executions(&self, address: usize) -> u64 {
+        self.executions.get(address).copied().unwrap_or_default()
+    }
+
+    /// Returns how many times `address` was read as an operand.
+    pub fn reads(&self, address: usize) -> u64 {
+        self.reads.get(address).copied().unwrap_or_default()
+    }
+
+    pub fn writes(&self, address: usize) -> u64 {
+        self.writes.get(address).copied().unwrap_or_default()
+    }
+
+    pub fn opcode(&self, opcode: Opcode) -> u64 {
+        self.opcodes.get(&opcode).copied().unwrap_or_default()
+    }
+
+    /// Returns the `n` most executed addresses with their counts, busiest first.
+    pub fn hot_spots(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut hot: Vec<(usize, u64)> = self
+            .executions
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot.truncate(n);
+        hot
+    }
+
+    /// Summarizes the profile, listing the `n` hot spots as they disassemble in `memory`.
+    pub fn report(&self, memory: &Memory, n: usize) -> String {
+        let disassembler = Disassembler::new(memory);
+        let mut report = format!("{} steps in {:?}\n", self.steps, self.elapsed);
+
+        let mut opcodes: Vec<(Opcode, u64)> =
+            self.opcodes.iter().map(|(&op, &n)| (op, n)).collect();
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.mnemonic().cmp(b.0.mnemonic())));
+        for (opcode, count) in opcodes {
+            report += &format!("{:>10}  {}\n", count, opcode);
+        }
+
+        report += "\n";
+        for (address, count) in self.hot_spots(n) {
+            let line = match memory.get(address) {
+                Some(_) => disassembler.line_at(address).to_string(),
+                None => format!("{:04}", address),
+            };
+            report += &format!("{:>10}  {}\n", count, line);
+        }
+        report
+    }
+
+    /// Exports the counts for every address that was touched as CSV.
+    pub fn to_csv(&self) -> String {
+        let len = self
+            .executions
+            .len()
+            .max(self.reads.len())
+            .max(self.writes.len());
+        let mut csv = String::from("address,executions,reads,writes\n");
+        for address in 0..len {
+            let counts = (
+                self.executions(address),
+                self.reads(address),
+                self.writes(address),
+            );
+            if counts != (0, 0, 0) {
+                csv += &format!("{},{},{},{}\n", address, counts.0, counts.1, counts.2);
+            }
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::intterpreter::IntTerpreter;
+
+    #[test]
+    fn test_profile() {
+        // Counts down from 3
+        let memory = assemble(
+            "
+            loop: add [n], #-1, [n]
+                  jt  [n], #loop
+                  out [n]
+                  hlt
+            n:    .data 3
+            ",
+        )
+        .unwrap();
+        let mut interpreter = IntTerpreter::new()
+            .with_memory(memory.clone())
+            .with_buffer()
+            .with_profile();
+        interpreter.execute();
+
+        let profile = interpreter.profile().unwrap();
+        assert_eq!(profile.steps(), 8);
+        assert_eq!(profile.opcode(Opcode::Add), 3);
+        assert_eq!(profile.opcode(Opcode::Bot), 3);
+        assert_eq!(profile.executions(4), 3);
+        assert_eq!(profile.executions(9), 1);
+        assert_eq!(profile.reads(10), 7);
+        assert_eq!(profile.writes(10), 3);
+        assert_eq!(profile.hot_spots(2), [(0, 3), (4, 3)]);
+
+        let report = profile.report(&memory, 2);
+        assert!(report.starts_with("8 steps in "));
+        assert!(report.ends_with(
+            "         3  add\n\
+             \x20        3  jt\n\
+             \x20        1  hlt\n\
+             \x20        1  out\n\
+             \n\
+             \x20        3  0000  add   [10], #-1, [10]\n\
+             \x20        3  0004  jt    [10], #0\n"
+        ));
+
+        assert_eq!(
+            profile.to_csv(),
+            "address,executions,reads,writes\n\
+             0,3,0,0\n\
+             4,3,0,0\n\
+             7,1,0,0\n\
+             9,1,0,0\n\
+             10,0,7,3\n"
+        );
+
+        // Nothing is recorded unless it's asked for
+        let mut interpreter = IntTerpreter::new().with_memory(memory).with_buffer();
+        interpreter.execute();
+        assert!(interpreter.profile().is_none());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::intterpreter::{Int, Memory, TraceEntry, Word};
+
+impl<W: Word> std::fmt::Display for TraceEntry<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operands = self
+            .operands
+            .iter()
+            .map(W::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let text = format!(
+            "{:04}  {:<5} {}",
+            self.ip,
+            self.instruction.opcode(),
+            operands
+        );
+        write!(f, "{}", text.trim_end())?;
+        for write in &self.writes {
+            write!(f, "  [{}] {} -> {}", write.address, write.old, write.new)?;
+        }
+        if let Some(input) = &self.input {
+            write!(f, "  in {}", input)?;
+        }
+        if let Some(output) = &self.output {
+            write!(f, "  out {}", output)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes a trace as JSON Lines, one entry per line.
+pub fn to_json_lines(trace: &[TraceEntry]) -> String {
+    trace
+        .iter()
+        .map(|entry| serde_json::to_string(entry).unwrap() + "\n")
+        .collect()
+}
+
+pub fn from_json_lines(s: &str) -> serde_json::Result<Vec<TraceEntry>> {
+    s.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect()
+}
+
+/// Moves through a recorded trace by applying and undoing its memory writes, without running
+/// the program again.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    memory: Memory,
+    trace: Vec<TraceEntry>,
+    position: usize,
+}
+
+impl Replay {
+    /// Starts a replay from `memory`, which must be the program as it was before the trace was
+    /// recorded.
+    pub fn new(memory: Memory, trace: Vec<TraceEntry>) -> Self {
+        Self {
+            memory,
+            trace,
+            position: 0,
+        }
+    }
+
+    /// Returns how many entries of the trace have been applied.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.trace.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trace.is_empty()
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Returns the entry that would be applied by the next [`step_forward`].
+    ///
+    /// [`step_forward`]: Replay::step_forward
+    pub fn current(&self) -> Option<&TraceEntry> {
+        self.trace.get(self.position)
+    }
+
+    pub fn step_forward(&mut self) -> Option<&TraceEntry> {
+        let entry = self.trace.get(self.position)?;
+        for write in &entry.writes {
+            self.memory[write.address] = write.new;
+        }
+        self.position += 1;
+        Some(entry)
+    }
+
+    pub fn step_back(&mut self) -> Option<&TraceEntry> {
+        self.position = self.position.checked_sub(1)?;
+        let entry = &self.trace[self.position];
+        for write in entry.writes.iter().rev() {
+            self.memory[write.address] = write.old;
+        }
+        Some(entry)
+    }
+
+    pub fn seek(&mut self, position: usize) {
+        let position = position.min(self.len());
+        while self.position < position {
+            self.step_forward();
+        }
+        while self.position > position {
+            self.step_back();
+        }
+    }
+
+    /// Returns everything output up to the current position.
+    pub fn outputs(&self) -> Vec<Int> {
+        self.trace[..self.position]
+            .iter()
+            .filter_map(|entry| entry.output)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a TraceEntry),
+    Changed(Option<&'a TraceEntry>, Option<&'a TraceEntry>),
+}
+
+/// Compares two traces step by step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceDiff<'a> {
+    lines: Vec<DiffLine<'a>>,
+}
+
+impl<'a> TraceDiff<'a> {
+    pub fn new(a: &'a [TraceEntry], b: &'a [TraceEntry]) -> Self {
+        let lines = (0..a.len().max(b.len()))
+            .map(|i| match (a.get(i), b.get(i)) {
+                (Some(a), Some(b)) if a == b => DiffLine::Same(a),
+                (a, b) => DiffLine::Changed(a, b),
+            })
+            .collect();
+        Self { lines }
+    }
+
+    pub fn lines(&self) -> &[DiffLine<'a>] {
+        &self.lines
+    }
+
+    /// Returns the index of the first step where the traces differ.
+    pub fn first_divergence(&self) -> Option<usize> {
+        self.lines
+            .iter()
+            .position(|line| matches!(line, DiffLine::Changed(..)))
+    }
+}
+
+impl std::fmt::Display for TraceDiff<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.lines {
+            match line {
+                DiffLine::Same(entry) => writeln!(f, "  {}", entry)?,
+                DiffLine::Changed(a, b) => {
+                    if let Some(a) = a {
+                        writeln!(f, "- {}", a)?;
+                    }
+                    if let Some(b) = b {
+                        writeln!(f, "+ {}", b)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intterpreter::IntTerpreter;
+
+    const PROGRAM: &str = "3,9,8,9,10,9,4,9,99,-1,8";
+
+    fn record(input: i64) -> (IntTerpreter, Vec<TraceEntry>) {
+        let mut interpreter = IntTerpreter::new()
+            .with_memory(Memory::try_from(PROGRAM).unwrap())
+            .with_input(&[input])
+            .with_buffer()
+            .with_trace();
+        interpreter.execute();
+        let trace = interpreter.take_trace().unwrap();
+        (interpreter, trace)
+    }
+
+    #[test]
+    fn test_record() {
+        let (_, trace) = record(8);
+        assert_eq!(
+            trace.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "0000  in    9  [9] -1 -> 8  in 8",
+                "0002  eq    8, 8, 9  [9] 8 -> 1",
+                "0006  out   1  out 1",
+                "0008  hlt",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let (_, trace) = record(8);
+        let json = to_json_lines(&trace);
+        assert_eq!(json.lines().count(), trace.len());
+        assert_eq!(from_json_lines(&json).unwrap(), trace);
+    }
+
+    #[test]
+    fn test_replay() {
+        let (interpreter, trace) = record(8);
+        let initial = Memory::try_from(PROGRAM).unwrap();
+        let mut replay = Replay::new(initial.clone(), trace);
+
+        replay.seek(replay.len());
+        assert_eq!(Some(replay.memory()), interpreter.memory());
+        assert_eq!(replay.outputs(), vec![Int::new(1)]);
+        assert!(replay.step_forward().is_none());
+
+        assert_eq!(replay.step_back().unwrap().ip, 8);
+        assert_eq!(replay.step_back().unwrap().ip, 6);
+        assert!(replay.outputs().is_empty());
+        assert_eq!(replay.memory()[9], Int::new(1));
+        assert_eq!(replay.step_back().unwrap().ip, 2);
+        assert_eq!(replay.memory()[9], Int::new(8));
+
+        replay.seek(0);
+        assert_eq!(replay.memory(), &initial);
+        assert!(replay.step_back().is_none());
+    }
+
+    #[test]
+    fn test_diff() {
+        let (_, a) = record(8);
+        let (_, b) = record(7);
+        let diff = TraceDiff::new(&a, &b);
+        assert_eq!(diff.first_divergence(), Some(0));
+        assert_eq!(
+            diff.to_string(),
+            "- 0000  in    9  [9] -1 -> 8  in 8\n\
+             + 0000  in    9  [9] -1 -> 7  in 7\n\
+             - 0002  eq    8, 8, 9  [9] 8 -> 1\n\
+             + 0002  eq    7, 8, 9  [9] 7 -> 0\n\
+             - 0006  out   1  out 1\n\
+             + 0006  out   0  out 0\n\
+             \x20 0008  hlt\n"
+        );
+    }
+}